@@ -0,0 +1,308 @@
+// Keeps the enabled features consistent with the `requires`, `conflicts` and exclusive groups of the
+// manifest's features and decides which items they and the platform enable
+use crate::platform::{self, Platform};
+use crate::{Condition, Feature};
+
+fn find<'a>(features: &'a [Feature], id: &str) -> Option<&'a Feature> {
+    features.iter().find(|x| x.id == id)
+}
+
+fn display_name<'a>(features: &'a [Feature], id: &'a str) -> &'a str {
+    find(features, id).map_or(id, |x| x.name.as_str())
+}
+
+/// Whether `a` and `b` are both part of the same exclusive group
+fn same_group(features: &[Feature], a: &str, b: &str) -> bool {
+    match (find(features, a), find(features, b)) {
+        (Some(a), Some(b)) => a.exclusive && b.exclusive && a.group.is_some() && a.group == b.group,
+        _ => false,
+    }
+}
+
+/// Conflicts only have to be declared on one of the two features
+fn conflicts(features: &[Feature], a: &str, b: &str) -> bool {
+    same_group(features, a, b)
+        || find(features, a).is_some_and(|x| x.conflicts.iter().any(|x| x == b))
+        || find(features, b).is_some_and(|x| x.conflicts.iter().any(|x| x == a))
+}
+
+fn enable(
+    features: &[Feature],
+    enabled: &mut Vec<String>,
+    id: &str,
+    reason: Option<String>,
+    changes: &mut Vec<String>,
+) {
+    if enabled.iter().any(|x| x == id) {
+        return;
+    }
+    enabled.push(id.to_string());
+    changes.extend(reason);
+    let feature = match find(features, id) {
+        Some(feature) => feature,
+        None => return,
+    };
+    for required in &feature.requires {
+        let reason = format!(
+            "Enabled '{}' because '{}' requires it",
+            display_name(features, required),
+            feature.name
+        );
+        enable(features, enabled, required, Some(reason), changes);
+    }
+    for other in features {
+        if other.id != id && enabled.contains(&other.id) && conflicts(features, id, &other.id) {
+            // Switching between the options of a group doesn't need an explanation
+            let reason = (!same_group(features, id, &other.id)).then(|| {
+                format!(
+                    "Disabled '{}' because it conflicts with '{}'",
+                    other.name, feature.name
+                )
+            });
+            disable(features, enabled, &other.id, reason, changes);
+        }
+    }
+}
+
+fn disable(
+    features: &[Feature],
+    enabled: &mut Vec<String>,
+    id: &str,
+    reason: Option<String>,
+    changes: &mut Vec<String>,
+) {
+    if !enabled.iter().any(|x| x == id) {
+        return;
+    }
+    enabled.retain(|x| x != id);
+    changes.extend(reason);
+    for other in features {
+        if enabled.contains(&other.id) && other.requires.iter().any(|x| x == id) {
+            let reason = format!(
+                "Disabled '{}' because it requires '{}'",
+                other.name,
+                display_name(features, id)
+            );
+            disable(features, enabled, &other.id, Some(reason), changes);
+        }
+    }
+}
+
+/// Enables or disables the feature `id`, enabling the features it requires and disabling the ones
+/// that conflict with it or depend on it
+///
+/// Returns an explanation for every other feature that was changed
+pub(crate) fn set_enabled(
+    features: &[Feature],
+    enabled: &mut Vec<String>,
+    id: &str,
+    value: bool,
+) -> Vec<String> {
+    let mut changes = vec![];
+    if value {
+        enable(features, enabled, id, None, &mut changes);
+    } else {
+        disable(features, enabled, id, None, &mut changes);
+    }
+    changes
+}
+
+/// Checks that the requirements of every enabled feature are enabled and that no enabled features conflict
+pub(crate) fn validate(features: &[Feature], enabled: &[String]) -> Result<(), String> {
+    let mut problems = vec![];
+    for feature in features.iter().filter(|x| enabled.contains(&x.id)) {
+        for required in feature.requires.iter().filter(|x| !enabled.contains(x)) {
+            problems.push(format!(
+                "'{}' requires '{}'",
+                feature.name,
+                display_name(features, required)
+            ));
+        }
+        for other in features.iter().filter(|x| enabled.contains(&x.id)) {
+            // Only report each pair once
+            if feature.id >= other.id {
+                continue;
+            }
+            if same_group(features, &feature.id, &other.id) {
+                problems.push(format!(
+                    "Only one of '{}' can be enabled but '{}' and '{}' are",
+                    feature.group.as_deref().unwrap_or_default(),
+                    feature.name,
+                    other.name
+                ));
+            } else if conflicts(features, &feature.id, &other.id) {
+                problems.push(format!(
+                    "'{}' conflicts with '{}'",
+                    feature.name, other.name
+                ));
+            }
+        }
+    }
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(format!(
+            "Invalid feature selection:\n{}",
+            problems.join("\n")
+        ))
+    }
+}
+
+/// Turns an invalid selection into a valid one by enabling the features in order, later features win
+/// over earlier ones they conflict with
+///
+/// Returns the repaired selection and an explanation for every feature that was changed
+pub(crate) fn repair(features: &[Feature], enabled: &[String]) -> (Vec<String>, Vec<String>) {
+    let mut repaired = vec![];
+    let mut changes = vec![];
+    for id in enabled {
+        changes.extend(set_enabled(features, &mut repaired, id, true));
+    }
+    (repaired, changes)
+}
+
+impl Condition {
+    pub(crate) fn is_met(&self, enabled_features: &[String]) -> bool {
+        self.is_met_on(enabled_features, platform::current())
+    }
+
+    fn is_met_on(&self, enabled_features: &[String], platform: &Platform) -> bool {
+        match self {
+            Condition::Feature(id) => enabled_features.contains(id),
+            Condition::List(all) | Condition::All { all } => {
+                all.iter().all(|x| x.is_met_on(enabled_features, platform))
+            }
+            Condition::Any { any } => any.iter().any(|x| x.is_met_on(enabled_features, platform)),
+            Condition::Not { not } => !not.is_met_on(enabled_features, platform),
+            Condition::Os { os } => platform.os == *os,
+            Condition::Arch { arch } => platform.arch == *arch,
+        }
+    }
+
+    /// Every feature id the condition refers to
+    pub(crate) fn ids(&self) -> Vec<&str> {
+        match self {
+            Condition::Feature(id) => vec![id.as_str()],
+            Condition::List(all) | Condition::All { all } | Condition::Any { any: all } => {
+                all.iter().flat_map(|x| x.ids()).collect()
+            }
+            Condition::Not { not } => not.ids(),
+            Condition::Os { .. } | Condition::Arch { .. } => vec![],
+        }
+    }
+}
+
+/// Whether an item with the feature `id` and `condition` is installed with the enabled features
+pub(crate) fn is_enabled(
+    id: &str,
+    condition: &Option<Condition>,
+    enabled_features: &[String],
+) -> bool {
+    enabled_features.iter().any(|x| x == id)
+        && condition
+            .as_ref()
+            .is_none_or(|x| x.is_met(enabled_features))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn features() -> Vec<Feature> {
+        serde_json::from_value(json!([
+            { "id": "shaders", "name": "Shaders", "default": true, "requires": ["iris"] },
+            { "id": "iris", "name": "Iris", "default": false },
+            { "id": "optifine", "name": "OptiFine", "default": true, "conflicts": ["iris"] },
+            { "id": "low", "name": "Low", "default": true, "group": "Quality", "exclusive": true },
+            { "id": "high", "name": "High", "default": true, "group": "Quality", "exclusive": true },
+        ]))
+        .unwrap()
+    }
+
+    #[test]
+    fn condition_is_met() {
+        let linux = Platform {
+            os: String::from("linux"),
+            arch: String::from("x86_64"),
+        };
+        let enabled = [String::from("iris"), String::from("low")];
+        let cases = [
+            (json!("iris"), true),
+            (json!("optifine"), false),
+            (json!(["iris", "low"]), true),
+            (json!(["iris", "high"]), false),
+            (json!({ "all": ["iris", "low"] }), true),
+            (json!({ "all": ["iris", "high"] }), false),
+            (json!({ "any": ["optifine", "low"] }), true),
+            (json!({ "any": ["optifine", "high"] }), false),
+            (json!({ "not": "optifine" }), true),
+            (json!({ "not": { "any": ["iris"] } }), false),
+            (json!({ "os": "linux" }), true),
+            (json!({ "os": "macos" }), false),
+            (json!({ "arch": "x86_64" }), true),
+            (json!({ "arch": "aarch64" }), false),
+            (
+                json!([{ "os": "linux" }, { "not": { "arch": "aarch64" } }]),
+                true,
+            ),
+            (json!({ "any": [{ "os": "windows" }, "optifine"] }), false),
+        ];
+        for (condition, expected) in cases {
+            let parsed: Condition = serde_json::from_value(condition.clone()).unwrap();
+            assert_eq!(parsed.is_met_on(&enabled, &linux), expected, "{condition}");
+        }
+    }
+
+    #[test]
+    fn condition_rejects_mixed_objects() {
+        for condition in [
+            json!({ "os": "linux", "arch": "x86_64" }),
+            json!({ "all": ["iris"], "any": ["low"] }),
+            json!({ "feature": "iris" }),
+        ] {
+            assert!(
+                serde_json::from_value::<Condition>(condition.clone()).is_err(),
+                "{condition}"
+            );
+        }
+    }
+
+    #[test]
+    fn is_enabled_requires_feature_and_condition() {
+        let enabled = [String::from("iris")];
+        let condition = |x| Some(serde_json::from_value::<Condition>(x).unwrap());
+        assert!(is_enabled("iris", &None, &enabled));
+        assert!(!is_enabled("optifine", &None, &enabled));
+        assert!(is_enabled("iris", &condition(json!("iris")), &enabled));
+        assert!(!is_enabled(
+            "iris",
+            &condition(json!({ "not": "iris" })),
+            &enabled
+        ));
+        assert!(!is_enabled("optifine", &condition(json!("iris")), &enabled));
+    }
+
+    #[test]
+    fn repair_explains_changes() {
+        let features = features();
+        let defaults: Vec<String> = features
+            .iter()
+            .filter(|x| x.default)
+            .map(|x| x.id.clone())
+            .collect();
+        assert!(validate(&features, &defaults).is_err());
+        let (repaired, changes) = repair(&features, &defaults);
+        assert_eq!(repaired, ["optifine", "high"]);
+        assert!(validate(&features, &repaired).is_ok());
+        assert_eq!(
+            changes,
+            [
+                "Enabled 'Iris' because 'Shaders' requires it",
+                "Disabled 'Iris' because it conflicts with 'OptiFine'",
+                "Disabled 'Shaders' because it requires 'Iris'",
+            ]
+        );
+    }
+}
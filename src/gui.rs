@@ -2,9 +2,12 @@ use std::{collections::BTreeMap, path::PathBuf};
 
 use base64::{engine, Engine};
 use dioxus::prelude::*;
+use log::{error, info};
 use modal::{Modal, ModalContext};
 
-use crate::{get_app_data, get_installed_packs, get_launcher, uninstall, Launcher, PackName};
+use crate::{
+    get_app_data, get_installed_packs, get_launcher, uninstall, Launcher, PackName, UninstallOptions,
+};
 
 mod modal;
 
@@ -63,7 +66,7 @@ fn Credits(mut props: CreditsProps) -> Element {
                         div { class: "credits-inner",
                             ul {
                                 for r#mod in props.manifest.mods {
-                                    if props.enabled.contains(&r#mod.id) {
+                                    if super::features::is_enabled(&r#mod.id, &r#mod.condition, &props.enabled) {
                                         li {
                                             "{r#mod.name} by "
                                             for author in &r#mod.authors {
@@ -79,7 +82,7 @@ fn Credits(mut props: CreditsProps) -> Element {
                                     }
                                 }
                                 for shaderpack in props.manifest.shaderpacks {
-                                    if props.enabled.contains(&shaderpack.id) {
+                                    if super::features::is_enabled(&shaderpack.id, &shaderpack.condition, &props.enabled) {
                                         li {
                                             "{shaderpack.name} by "
                                             for author in &shaderpack.authors {
@@ -95,7 +98,7 @@ fn Credits(mut props: CreditsProps) -> Element {
                                     }
                                 }
                                 for resourcepack in props.manifest.resourcepacks {
-                                    if props.enabled.contains(&resourcepack.id) {
+                                    if super::features::is_enabled(&resourcepack.id, &resourcepack.condition, &props.enabled) {
                                         li {
                                             "{resourcepack.name} by "
                                             for author in &resourcepack.authors {
@@ -111,7 +114,7 @@ fn Credits(mut props: CreditsProps) -> Element {
                                     }
                                 }
                                 for include in props.manifest.include {
-                                    if props.enabled.contains(&include.id) && include.authors.is_some()
+                                    if super::features::is_enabled(&include.id, &include.condition, &props.enabled) && include.authors.is_some()
                                         && include.name.is_some()
                                     {
                                         li {
@@ -138,7 +141,7 @@ fn Credits(mut props: CreditsProps) -> Element {
 }
 
 #[component]
-fn PackUninstallButton(launcher: Launcher, pack: PackName) -> Element {
+fn PackUninstallButton(launcher: Launcher, pack: PackName, options: Signal<UninstallOptions>) -> Element {
     // TODO: Handle uninstall error
     let mut hidden = use_signal(|| false);
     rsx!(
@@ -146,7 +149,7 @@ fn PackUninstallButton(launcher: Launcher, pack: PackName) -> Element {
             button {
                 class: "pack-uninstall",
                 onclick: move |_| {
-                    uninstall(&launcher, &pack.uuid).unwrap();
+                    uninstall(&launcher, &pack.uuid, *options.read()).unwrap();
                     *hidden.write() = true;
                 },
                 "{pack.name}"
@@ -166,11 +169,25 @@ struct SettingsProps {
 
 #[component]
 fn Settings(mut props: SettingsProps) -> Element {
-    let mut vanilla = None;
-    let mut multimc = None;
-    let mut prism = None;
+    let launchers = crate::launcher::discover_launchers();
     let mut custom = None;
     let launcher = get_launcher(&props.config.read().launcher).unwrap();
+    let scan_launcher = launcher.clone();
+    // Checking every java found takes a while, so the scan doesn't run on the UI thread
+    let runtimes = use_resource(move || {
+        let launcher = scan_launcher.clone();
+        async move {
+            let (sender, receiver) = futures::channel::oneshot::channel();
+            std::thread::spawn(move || {
+                let _ = sender.send(crate::java::discover_java(Some(&launcher)));
+            });
+            receiver.await.unwrap_or_default()
+        }
+    });
+    let mut uninstall_options = use_signal(|| UninstallOptions {
+        keep_saves: true,
+        keep_screenshots: true,
+    });
     let packs = match get_installed_packs(&launcher) {
         Ok(v) => v,
         Err(err) => {
@@ -178,25 +195,61 @@ fn Settings(mut props: SettingsProps) -> Element {
             return None;
         }
     };
-    match &props.config.read().launcher[..] {
-        "vanilla" => vanilla = Some("true"),
-        "multimc-MultiMC" => multimc = Some("true"),
-        "multimc-PrismLauncher" => prism = Some("true"),
-        _ => {}
-    }
-    if props.config.read().launcher.starts_with("custom") {
+    let current_launcher = props.config.read().launcher.clone();
+    if current_launcher.starts_with("custom") && !launchers.iter().any(|x| x.id == current_launcher) {
         custom = Some("true")
     }
 
+    let java_searching = runtimes.read().is_none();
+    let mut java_options: Vec<(PathBuf, String)> = runtimes
+        .read()
+        .iter()
+        .flatten()
+        .map(|x| (x.path.clone(), format!("Java {} ({})", x.version, x.path.display())))
+        .collect();
+    for java in props.config.read().java.values() {
+        if !java_options.iter().any(|(path, _)| path == java) {
+            java_options.push((java.clone(), java.display().to_string()));
+        }
+    }
+    let java_packs = packs.clone();
+    let java_launcher = launcher.clone();
+    // The executable field shows the override of the launcher that was selected when the settings
+    // were opened, so it is saved for that launcher even if another one is selected
+    let executable_launcher = current_launcher.clone();
     rsx! {
         div { class: "container", style: "width: 24vw;",
             form {
                 id: "settings",
                 onsubmit: move |event| {
-                    props
-                        .config
-                        .write()
-                        .launcher = event.data.values()["launcher-select"].as_value();
+                    let values = event.data.values();
+                    props.config.write().launcher = values["launcher-select"].as_value();
+                    match values.get("launcher-executable") {
+                        Some(v) if !v.as_value().trim().is_empty() => {
+                            props.config.write().launcher_executables.insert(executable_launcher.clone(), PathBuf::from(v.as_value().trim()))
+                        }
+                        _ => props.config.write().launcher_executables.remove(&executable_launcher),
+                    };
+                    for pack in &java_packs {
+                        match values.get(&format!("name-{}", pack.uuid)) {
+                            Some(v) if !v.as_value().trim().is_empty() => {
+                                props.config.write().instance_names.insert(pack.uuid.clone(), v.as_value().trim().to_string())
+                            }
+                            _ => props.config.write().instance_names.remove(&pack.uuid),
+                        };
+                        let java = match values.get(&format!("java-{}", pack.uuid)) {
+                            Some(v) if !v.as_value().is_empty() => Some(PathBuf::from(v.as_value())),
+                            _ => None,
+                        };
+                        if props.config.read().java.get(&pack.uuid) == java.as_ref() {
+                            continue;
+                        }
+                        crate::java::set_configured_java(&java_launcher, &pack.uuid, java.as_deref());
+                        match java {
+                            Some(java) => props.config.write().java.insert(pack.uuid.clone(), java),
+                            None => props.config.write().java.remove(&pack.uuid),
+                        };
+                    }
                     if let Err(e) = std::fs::write(
                         &props.config_path,
                         serde_json::to_vec(&*props.config.read()).unwrap(),
@@ -212,17 +265,11 @@ fn Settings(mut props: SettingsProps) -> Element {
                         id: "launcher-select",
                         form: "settings",
                         class: "credits-button",
-                        if super::get_minecraft_folder().is_dir() {
-                            option { value: "vanilla", selected: vanilla, "Vanilla" }
-                        }
-                        if super::get_multimc_folder("MultiMC").is_ok() {
-                            option { value: "multimc-MultiMC", selected: multimc, "MultiMC" }
-                        }
-                        if super::get_multimc_folder("PrismLauncher").is_ok() {
+                        for launcher in launchers {
                             option {
-                                value: "multimc-PrismLauncher",
-                                selected: prism,
-                                "Prism Launcher"
+                                value: "{launcher.id}",
+                                selected: (launcher.id == current_launcher).then_some("true"),
+                                "{launcher.name}"
                             }
                         }
                         if custom.is_some() {
@@ -234,12 +281,73 @@ fn Settings(mut props: SettingsProps) -> Element {
                         }
                     }
                 }
+                div { class: "label",
+                    span { "Launcher executable:" }
+                    input {
+                        r#type: "text",
+                        name: "launcher-executable",
+                        form: "settings",
+                        class: "credits-button",
+                        placeholder: "Detect automatically",
+                        value: props
+                            .config
+                            .read()
+                            .launcher_executables
+                            .get(&current_launcher)
+                            .map(|x| x.display().to_string())
+                            .unwrap_or_default()
+                    }
+                }
                 CustomMultiMCButton {
                     config: props.config,
                     config_path: props.config_path.clone(),
                     error: props.error,
                     b64_id: props.b64_id.clone()
                 }
+                div { class: "label feature-list",
+                    label {
+                        input {
+                            r#type: "checkbox",
+                            checked: props.config.read().managed_java,
+                            onchange: move |evt| {
+                                props.config.write().managed_java = evt.data.value() == "true";
+                            }
+                        }
+                        "Download Java automatically for MultiMC packs"
+                    }
+                }
+                for pack in packs.clone() {
+                    div { class: "label",
+                        span { "{pack.name} instance name:" }
+                        input {
+                            r#type: "text",
+                            name: "name-{pack.uuid}",
+                            form: "settings",
+                            class: "credits-button",
+                            placeholder: "Default",
+                            value: props.config.read().instance_names.get(&pack.uuid).cloned().unwrap_or_default()
+                        }
+                    }
+                    div { class: "label",
+                        span { "{pack.name} Java:" }
+                        select {
+                            name: "java-{pack.uuid}",
+                            form: "settings",
+                            class: "credits-button",
+                            option { value: "", "Launcher default" }
+                            if java_searching {
+                                option { value: "", disabled: true, "Searching for Java..." }
+                            }
+                            for (path , label) in java_options.clone() {
+                                option {
+                                    value: "{path.display()}",
+                                    selected: if props.config.read().java.get(&pack.uuid) == Some(&path) { Some("true") } else { None },
+                                    "{label}"
+                                }
+                            }
+                        }
+                    }
+                }
                 input {
                     r#type: "submit",
                     value: "Save",
@@ -256,9 +364,31 @@ fn Settings(mut props: SettingsProps) -> Element {
                             .open(
                                 "Select modpack to uninstall.",
                                 rsx! {
+                                    div { class: "label feature-list",
+                                        label {
+                                            input {
+                                                r#type: "checkbox",
+                                                checked: uninstall_options.read().keep_saves,
+                                                onchange: move |evt| {
+                                                    uninstall_options.write().keep_saves = evt.data.value() == "true";
+                                                }
+                                            }
+                                            "Keep saves"
+                                        }
+                                        label {
+                                            input {
+                                                r#type: "checkbox",
+                                                checked: uninstall_options.read().keep_screenshots,
+                                                onchange: move |evt| {
+                                                    uninstall_options.write().keep_screenshots = evt.data.value() == "true";
+                                                }
+                                            }
+                                            "Keep screenshots"
+                                        }
+                                    }
                                     ul {
                                         for pack in packs.clone() {
-                                            PackUninstallButton { launcher: launcher.clone(), pack }
+                                            PackUninstallButton { launcher: launcher.clone(), pack, options: uninstall_options }
                                         }
                                     }
                                 },
@@ -284,19 +414,9 @@ struct LauncherProps {
 
 #[component]
 fn Launcher(mut props: LauncherProps) -> Element {
-    let mut vanilla = None;
-    let mut multimc = None;
-    let mut prism = None;
-    match &props.config.read().launcher[..] {
-        "vanilla" => vanilla = Some("true"),
-        "multimc-MultiMC" => multimc = Some("true"),
-        "multimc-PrismLauncher" => prism = Some("true"),
-        _ => {}
-    }
-    let has_supported_launcher = super::get_minecraft_folder().is_dir()
-        || super::get_multimc_folder("MultiMC").is_ok()
-        || super::get_multimc_folder("PrismLauncher").is_ok();
-    if !has_supported_launcher {
+    let launchers = crate::launcher::discover_launchers();
+    let current_launcher = props.config.read().launcher.clone();
+    if launchers.is_empty() {
         rsx!(NoLauncherFound {
             config: props.config,
             config_path: props.config_path,
@@ -328,21 +448,11 @@ fn Launcher(mut props: LauncherProps) -> Element {
                             id: "launcher-select",
                             form: "settings",
                             class: "credits-button",
-                            if super::get_minecraft_folder().is_dir() {
-                                option { value: "vanilla", selected: vanilla, "Vanilla" }
-                            }
-                            if super::get_multimc_folder("MultiMC").is_ok() {
+                            for launcher in launchers {
                                 option {
-                                    value: "multimc-MultiMC",
-                                    selected: multimc,
-                                    "MultiMC"
-                                }
-                            }
-                            if super::get_multimc_folder("PrismLauncher").is_ok() {
-                                option {
-                                    value: "multimc-PrismLauncher",
-                                    selected: prism,
-                                    "Prism Launcher"
+                                    value: "{launcher.id}",
+                                    selected: (launcher.id == current_launcher).then_some("true"),
+                                    "{launcher.name}"
                                 }
                             }
                         }
@@ -413,7 +523,7 @@ fn NoLauncherFound(props: LauncherProps) -> Element {
         div { class: "container", style: "width: 48vw;",
             h1 { "No supported launcher found!" }
             p {
-                "Only Prism Launcher, MultiMC and the vanilla launcher are supported by default, other MultiMC launchers can be added using the button below."
+                "Only Prism Launcher, MultiMC, ATLauncher, GDLauncher, the Modrinth App and the vanilla launcher are supported by default, other MultiMC launchers can be added using the button below."
                 br {}
                 br {}
                 "If you have any of these installed then please make sure you are on the latest version of the installer, if you are, open a thread in #📂modpack-issues on the discord. Please make sure your thread contains the following information: Launcher your having issues with, directory of the launcher and your OS."
@@ -433,42 +543,116 @@ fn feature_change(
     mut modify: Signal<bool>,
     evt: FormEvent,
     feat: &super::Feature,
-    mut modify_count: Signal<i32>,
+    features: Signal<Vec<super::Feature>>,
     mut enabled_features: Signal<Vec<String>>,
+    mut feature_notices: Signal<Vec<String>>,
 ) {
     let enabled = match &*evt.data.value() {
         "true" => true,
         "false" => false,
         _ => panic!("Invalid bool from feature"),
     };
-    if enabled {
-        enabled_features.with_mut(|x| {
-            if !x.contains(&feat.id) {
-                x.push(feat.id.clone());
-            }
-        })
-    } else {
-        enabled_features.with_mut(|x| {
-            if x.contains(&feat.id) {
-                x.retain(|x| x != &feat.id);
-            }
-        })
+    let changes = enabled_features.with_mut(|x| {
+        super::features::set_enabled(&features.read(), x, &feat.id, enabled)
+    });
+    feature_notices.set(changes);
+    if let Some(local) = &*local_features.read() {
+        modify.set(enabled_features.with(|x| {
+            x.len() != local.len() || x.iter().any(|id| !local.contains(id))
+        }));
     }
-    if local_features.read().is_some() {
-        let modify_res = local_features.unwrap().contains(&feat.id) != enabled;
-        if modify_count.with(|x| *x <= 1) {
-            modify.set(local_features.unwrap().contains(&feat.id) != enabled);
+}
+
+/// Visible features grouped by their `group` in the order the groups first appear
+fn group_features(features: &[super::Feature]) -> Vec<(Option<String>, Vec<super::Feature>)> {
+    let mut groups: Vec<(Option<String>, Vec<super::Feature>)> = vec![];
+    for feat in features.iter().filter(|x| !x.hidden) {
+        match groups
+            .iter_mut()
+            .find(|(group, _)| group.is_some() && *group == feat.group)
+        {
+            Some((_, group_features)) => group_features.push(feat.clone()),
+            None => groups.push((feat.group.clone(), vec![feat.clone()])),
         }
-        if modify_res {
-            modify_count.with_mut(|x| *x += 1);
-        } else {
-            modify_count.with_mut(|x| *x -= 1);
+    }
+    groups
+}
+
+#[component]
+fn RemoveFileButton(path: PathBuf) -> Element {
+    let mut hidden = use_signal(|| false);
+    rsx!(
+        li { hidden,
+            "{path.display()} "
+            button {
+                class: "pack-uninstall",
+                r#type: "button",
+                onclick: move |_| {
+                    match std::fs::remove_file(&path) {
+                        Ok(_) => {
+                            info!("Removed untracked file: {path:#?}");
+                            *hidden.write() = true;
+                        }
+                        Err(e) => error!("Failed to remove {path:#?}: {e}"),
+                    }
+                },
+                "Remove"
+            }
         }
+    )
+}
+
+fn open_install_report(modal: &mut ModalContext, report: &super::InstallReport) {
+    if report.is_empty() {
+        return;
     }
+    modal.open(
+        "Warning",
+        rsx! {
+            if !report.incompatible_mods.is_empty() {
+                p { "The following mods are incompatible with this version of the modpack and may crash the game:" }
+                ul {
+                    for incompatible in report.incompatible_mods.clone() {
+                        li { "{incompatible}" }
+                    }
+                }
+            }
+            if !report.duplicate_mods.is_empty() {
+                p { "The following mods are installed more than once, which prevents the game from starting:" }
+                ul {
+                    for duplicate in report.duplicate_mods.clone() {
+                        li {
+                            "{duplicate.id}"
+                            ul {
+                                for file in duplicate.files.clone() {
+                                    if duplicate.untracked.contains(&file) {
+                                        RemoveFileButton { path: file }
+                                    } else {
+                                        li { "{file.display()}" }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+            if !report.conflicting_mods.is_empty() {
+                p { "The following mods conflict with each other:" }
+                ul {
+                    for conflict in report.conflicting_mods.clone() {
+                        li { "{conflict}" }
+                    }
+                }
+            }
+        },
+        false,
+        None::<fn(bool)>,
+    );
 }
 
 #[derive(PartialEq, Props, Clone)]
 struct VersionProps {
+    config: Signal<super::Config>,
     modpack_source: String,
     modpack_branch: String,
     launcher: super::Launcher,
@@ -563,7 +747,8 @@ fn Version(mut props: VersionProps) -> Element {
     let mut progress_status = use_signal(|| "");
     let mut install_progress = use_signal(|| 0);
     let mut modify = use_signal(|| false);
-    let mut modify_count = use_signal(|| 0);
+    let features = use_signal(|| installer_profile.manifest.features.clone());
+    let feature_notices = use_signal(|| installer_profile.feature_notices.clone());
     let enabled_features = use_signal(|| {
         if installer_profile.installed {
             installer_profile
@@ -587,6 +772,21 @@ fn Version(mut props: VersionProps) -> Element {
             None
         }
     });
+    let modal = use_context::<ModalContext>();
+    let mut play_modal = modal.clone();
+    let play_launcher = installer_profile
+        .launcher
+        .clone()
+        .expect("Launcher not selected!");
+    let play_uuid = installer_profile.manifest.uuid.clone();
+    let on_play = move |evt: MouseEvent| {
+        let config = props.config.read();
+        let executable = config.launcher_executables.get(&config.launcher);
+        if let Err(e) = play_launcher.launch(&play_uuid, executable.map(|x| x.as_path())) {
+            play_modal.open("Error", rsx! { p { "{e}" } }, false, None::<fn(bool)>);
+        }
+        evt.stop_propagation();
+    };
     let movable_profile = installer_profile.clone();
     let on_submit = move |_| {
         // TODO: Don't do naive item amount calculation
@@ -596,9 +796,11 @@ fn Version(mut props: VersionProps) -> Element {
             + movable_profile.manifest.include.len();
         let movable_profile = movable_profile.clone();
         let movable_profile2 = movable_profile.clone();
+        let modal = modal.clone();
         async move {
             let install = move |canceled| {
                 let mut installer_profile = movable_profile.clone();
+                let mut modal = modal.clone();
                 spawn(async move {
                     if canceled {
                         return;
@@ -606,6 +808,19 @@ fn Version(mut props: VersionProps) -> Element {
                     installing.set(true);
                     installer_profile.enabled_features = enabled_features.read().clone();
                     installer_profile.manifest.enabled_features = enabled_features.read().clone();
+                    installer_profile.java_path = props
+                        .config
+                        .read()
+                        .java
+                        .get(&installer_profile.manifest.uuid)
+                        .cloned();
+                    installer_profile.managed_java = props.config.read().managed_java;
+                    installer_profile.instance_name = props
+                        .config
+                        .read()
+                        .instance_names
+                        .get(&installer_profile.manifest.uuid)
+                        .cloned();
                     local_features.set(Some(enabled_features.read().clone()));
 
                     if !*installed.read() {
@@ -615,7 +830,8 @@ fn Version(mut props: VersionProps) -> Element {
                         })
                         .await
                         {
-                            Ok(_) => {
+                            Ok(report) => {
+                                open_install_report(&mut modal, &report);
                                 let _ = isahc::post(
                                     "https://tracking.commander07.workers.dev/track",
                                     format!(
@@ -652,7 +868,8 @@ fn Version(mut props: VersionProps) -> Element {
                         })
                         .await
                         {
-                            Ok(_) => {
+                            Ok(report) => {
+                                open_install_report(&mut modal, &report);
                                 let _ = isahc::post(
                                     "https://tracking.commander07.workers.dev/track",
                                     format!(
@@ -687,7 +904,8 @@ fn Version(mut props: VersionProps) -> Element {
                         })
                         .await
                         {
-                            Ok(_) => {
+                            Ok(report) => {
+                                open_install_report(&mut modal, &report);
                                 let _ = isahc::post(
                                     "https://tracking.commander07.workers.dev/track",
                                     format!(
@@ -713,7 +931,6 @@ fn Version(mut props: VersionProps) -> Element {
                             }
                         }
                         modify.with_mut(|x| *x = false);
-                        modify_count.with_mut(|x| *x = 0);
                         update_available.set(false);
                     }
                     installing.set(false);
@@ -788,27 +1005,28 @@ fn Version(mut props: VersionProps) -> Element {
                                     "Optional features:"
                                 }
                                 div { class: "feature-list",
-                                    for feat in installer_profile.manifest.features {
-                                        if !feat.hidden {
+                                    for (group, group_features) in group_features(&installer_profile.manifest.features) {
+                                        if let Some(group) = &group {
+                                            p { class: "feature-group", "{group}:" }
+                                        }
+                                        for feat in group_features {
                                             label { class: "tooltip",
                                                 input {
-                                                    checked: if installer_profile.installed {
-                                                        if enabled_features.with(|x| x.contains(&feat.id)) { Some("true") } else { None }
-                                                    } else {
-                                                        if feat.default { Some("true") } else { None }
-                                                    },
-                                                    name: "{feat.id}",
+                                                    checked: if enabled_features.with(|x| x.contains(&feat.id)) { Some("true") } else { None },
+                                                    name: if feat.exclusive && group.is_some() { "group-{group.as_ref().unwrap()}" } else { "{feat.id}" },
+                                                    value: "true",
+                                                    r#type: if feat.exclusive && group.is_some() { "radio" } else { "checkbox" },
                                                     onchange: move |evt| {
                                                         feature_change(
                                                             local_features,
                                                             modify,
                                                             evt,
                                                             &feat,
-                                                            modify_count,
+                                                            features,
                                                             enabled_features,
+                                                            feature_notices,
                                                         )
-                                                    },
-                                                    r#type: "checkbox"
+                                                    }
                                                 }
 
                                                 "{feat.name}"
@@ -823,8 +1041,14 @@ fn Version(mut props: VersionProps) -> Element {
                                         }
                                     }
                                 }
+                                for notice in feature_notices.read().iter() {
+                                    p { class: "feature-notice", "{notice}" }
+                                }
                             }
                         }
+                        if let Some(warning) = installer_profile.java_warning {
+                            p { class: "java-warning", "{warning}" }
+                        }
                         input {
                             r#type: "submit",
                             value: if !installer_profile.installed {
@@ -835,6 +1059,14 @@ fn Version(mut props: VersionProps) -> Element {
                             class: "install-button",
                             disabled: install_disable
                         }
+                        if *installed.read() {
+                            button {
+                                class: "install-button",
+                                r#type: "button",
+                                onclick: on_play,
+                                "Play"
+                            }
+                        }
                     }
                 }
             }
@@ -966,6 +1198,7 @@ pub(crate) fn app() -> Element {
             div { class: "fake-body",
                 for i in 0..branches.len() {
                     Version {
+                        config,
                         modpack_source: props.modpack_source.clone(),
                         modpack_branch: branches[i].name.clone(),
                         launcher: launcher.as_ref().unwrap().clone(),
@@ -0,0 +1,83 @@
+// Helpers for MultiMC style INI files ('instance.cfg', 'multimc.cfg', ...)
+use std::{collections::HashMap, fs, io, path::Path};
+
+use log::info;
+
+/// Reads `key` from the config, empty values are treated as missing
+pub(crate) fn read_value(path: &Path, key: &str) -> Option<String> {
+    fs::read_to_string(path).ok()?.lines().find_map(|line| {
+        let (k, v) = line.split_once('=')?;
+        if k.trim() == key && !v.trim().is_empty() {
+            Some(v.trim().to_string())
+        } else {
+            None
+        }
+    })
+}
+
+/// Sets (`Some`) or removes (`None`) keys in the config while keeping every other line intact
+pub(crate) fn set_values(path: &Path, values: &[(&str, Option<&str>)]) -> io::Result<()> {
+    let contents = match fs::read_to_string(path) {
+        Ok(v) => v,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e),
+    };
+    let mut written = vec![];
+    let mut lines: Vec<String> = contents
+        .lines()
+        .filter_map(|line| {
+            let key = match line.split_once('=') {
+                Some((k, _)) => k.trim(),
+                None => return Some(line.to_string()),
+            };
+            match values.iter().find(|(k, _)| *k == key) {
+                Some((k, Some(v))) => {
+                    written.push(*k);
+                    Some(format!("{k}={v}"))
+                }
+                Some((_, None)) => None,
+                None => Some(line.to_string()),
+            }
+        })
+        .collect();
+    for (k, v) in values {
+        if let Some(v) = v {
+            if !written.contains(k) {
+                lines.push(format!("{k}={v}"));
+            }
+        }
+    }
+    fs::write(path, lines.join("\n"))
+}
+
+/// Sets keys the installer manages, keys the user changed since the last time they were set are left alone
+///
+/// The values that were written are stored in `record_path`, without a record every key is assumed to be unchanged
+pub(crate) fn set_managed_values(
+    path: &Path,
+    record_path: &Path,
+    values: &[(&str, Option<String>)],
+) -> io::Result<()> {
+    let record: Option<HashMap<String, String>> = fs::read_to_string(record_path)
+        .ok()
+        .and_then(|x| serde_json::from_str(&x).ok());
+    let mut new_record = HashMap::new();
+    let mut updates = vec![];
+    for (key, value) in values {
+        if let Some(record) = &record {
+            if read_value(path, key).as_ref() != record.get(*key) {
+                info!("Keeping user customized '{key}' in {path:#?}");
+                if let Some(v) = record.get(*key) {
+                    new_record.insert(key.to_string(), v.clone());
+                }
+                continue;
+            }
+        }
+        if let Some(v) = value {
+            new_record.insert(key.to_string(), v.clone());
+        }
+        updates.push((*key, value.as_deref()));
+    }
+    set_values(path, &updates)?;
+    fs::write(record_path, serde_json::to_string(&new_record)?)
+}
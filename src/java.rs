@@ -0,0 +1,606 @@
+use std::{
+    env,
+    fs::{self, File},
+    io::Write,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use cached::proc_macro::cached;
+use cached::SizedCache;
+use flate2::read::GzDecoder;
+use futures::AsyncReadExt;
+use isahc::{http::StatusCode, AsyncReadResponseExt};
+use log::{info, warn};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use sha2::{Digest, Sha256};
+
+use crate::{get_app_data, get_minecraft_folder, CachedHttpClient, Launcher};
+
+const MOJANG_VERSION_MANIFEST: &str =
+    "https://piston-meta.mojang.com/mc/game/version_manifest_v2.json";
+const ADOPTIUM_API: &str = "https://api.adoptium.net/";
+
+#[derive(Debug, Deserialize, Serialize)]
+struct MojangVersion {
+    id: String,
+    url: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct MojangVersionManifest {
+    versions: Vec<MojangVersion>,
+}
+
+#[allow(non_snake_case)]
+#[derive(Debug, Deserialize, Serialize)]
+struct MojangJavaVersion {
+    majorVersion: u32,
+}
+
+#[allow(non_snake_case)]
+#[derive(Debug, Deserialize, Serialize)]
+struct MojangVersionInfo {
+    // Versions older than 1.17 don't specify a java version
+    javaVersion: Option<MojangJavaVersion>,
+}
+
+/// Fetches Mojang's version json for a Minecraft version
+pub(crate) async fn get_minecraft_version_json(
+    minecraft_version: &str,
+    http_client: &CachedHttpClient,
+) -> Result<JsonValue, String> {
+    let manifest: MojangVersionManifest = serde_json::from_str(
+        &http_client
+            .get_async(MOJANG_VERSION_MANIFEST)
+            .await
+            .map_err(|e| e.to_string())?
+            .text()
+            .await
+            .map_err(|e| e.to_string())?,
+    )
+    .map_err(|e| e.to_string())?;
+    let version = manifest
+        .versions
+        .iter()
+        .find(|x| x.id == minecraft_version)
+        .ok_or(format!("Unknown Minecraft version '{minecraft_version}'"))?;
+    serde_json::from_str(
+        &http_client
+            .get_async(version.url.as_str())
+            .await
+            .map_err(|e| e.to_string())?
+            .text()
+            .await
+            .map_err(|e| e.to_string())?,
+    )
+    .map_err(|e| e.to_string())
+}
+
+/// Gets the java major version Mojang requires for a Minecraft version
+pub(crate) async fn get_required_java_version(
+    minecraft_version: &str,
+    http_client: &CachedHttpClient,
+) -> Result<u32, String> {
+    let info: MojangVersionInfo =
+        serde_json::from_value(get_minecraft_version_json(minecraft_version, http_client).await?)
+            .map_err(|e| e.to_string())?;
+    Ok(info.javaVersion.map_or(8, |x| x.majorVersion))
+}
+
+/// Runs `java -version` and returns the major version
+pub(crate) fn get_java_version(java: &Path) -> Option<u32> {
+    let mut command = Command::new(java);
+    command.arg("-version");
+    #[cfg(target_os = "windows")]
+    {
+        use std::os::windows::process::CommandExt;
+        // CREATE_NO_WINDOW, stops a console from flashing up
+        command.creation_flags(0x08000000);
+    }
+    let output = match command.output() {
+        Ok(v) => v,
+        Err(e) => {
+            warn!("Failed to run '{java:#?} -version': {e}");
+            return None;
+        }
+    };
+    // java prints the version to stderr
+    let output = String::from_utf8_lossy(&output.stderr);
+    let re = Regex::new(r#"version "([^"]+)""#).unwrap(); // wont error pattern is valid
+    let version = &re.captures(&output)?[1];
+    let mut parts = version.split(['.', '_', '-', '+']);
+    let major = match parts.next()? {
+        // Java 8 and older report as '1.x'
+        "1" => parts.next()?,
+        x => x,
+    };
+    major.parse().ok()
+}
+
+/// Returns the java executable the launcher has been told to use for the pack, `None` means the launcher picks one itself
+pub(crate) fn get_configured_java(launcher: &Launcher, uuid: &str) -> Option<PathBuf> {
+    launcher.backend().java_path(uuid)
+}
+
+/// Returns a warning if the java configured for the pack is older than what the pack's Minecraft version needs
+pub(crate) async fn check_java(
+    launcher: &Launcher,
+    uuid: &str,
+    minecraft_version: &str,
+    http_client: &CachedHttpClient,
+) -> Option<String> {
+    java_warning(
+        get_configured_java(launcher, uuid)?,
+        minecraft_version,
+        http_client,
+    )
+    .await
+}
+
+// Every branch of the modpack is checked, usually with the same java and Minecraft version
+#[cached(
+    ty = "SizedCache<String, Option<String>>",
+    create = "{ SizedCache::with_size(20) }",
+    convert = r#"{ format!("{}|{}", java.display(), minecraft_version) }"#,
+    sync_writes = true
+)]
+async fn java_warning(
+    java: PathBuf,
+    minecraft_version: &str,
+    http_client: &CachedHttpClient,
+) -> Option<String> {
+    let required = match get_required_java_version(minecraft_version, http_client).await {
+        Ok(v) => v,
+        Err(e) => {
+            warn!("Failed to get required java version: {e}");
+            return None;
+        }
+    };
+    let version = get_java_version(&java);
+    info!("Configured java: {java:#?}, version: {version:?}, required: {required}");
+    match version {
+        Some(version) if version >= required => None,
+        Some(version) => Some(format!(
+            "Minecraft {minecraft_version} requires Java {required} but your launcher is set to use Java {version} ({}). Please change the Java used for this modpack.",
+            java.display()
+        )),
+        None => Some(format!(
+            "Minecraft {minecraft_version} requires Java {required} but the Java your launcher is set to use ({}) could not be run.",
+            java.display()
+        )),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct JavaRuntime {
+    pub path: PathBuf,
+    pub version: u32,
+}
+
+fn java_executable(bin: &Path) -> Option<PathBuf> {
+    let java = if cfg!(target_os = "windows") {
+        bin.join("java.exe")
+    } else {
+        bin.join("java")
+    };
+    if java.is_file() {
+        Some(java)
+    } else {
+        None
+    }
+}
+
+/// Use javaw on windows so launchers don't open a console window
+fn windowless_java(java: PathBuf) -> PathBuf {
+    let javaw = java.with_file_name("javaw.exe");
+    if javaw.is_file() {
+        javaw
+    } else {
+        java
+    }
+}
+
+/// Finds java executables in `bin` directories up to `depth` levels below `dir`
+fn find_java_executables(dir: &Path, depth: usize, found: &mut Vec<PathBuf>) {
+    if let Some(java) = java_executable(&dir.join("bin")) {
+        found.push(java);
+        return;
+    }
+    if depth == 0 {
+        return;
+    }
+    if let Ok(entries) = fs::read_dir(dir) {
+        for entry in entries.flatten() {
+            if entry.path().is_dir() {
+                find_java_executables(&entry.path(), depth - 1, found);
+            }
+        }
+    }
+}
+
+/// Directories java installs are commonly found under
+fn java_search_dirs(launcher: Option<&Launcher>) -> Vec<PathBuf> {
+    let mut search_dirs = vec![
+        get_minecraft_folder().join("runtime"),
+        get_managed_java_dir(),
+    ];
+    if let Some(home) = dirs::home_dir() {
+        search_dirs.push(home.join(".jdks"));
+        search_dirs.push(home.join(".sdkman/candidates/java"));
+    }
+    match env::consts::OS {
+        "windows" => {
+            for program_files in ["ProgramFiles", "ProgramFiles(x86)"] {
+                if let Ok(program_files) = env::var(program_files) {
+                    let program_files = PathBuf::from(program_files);
+                    for vendor in [
+                        "Java",
+                        "Eclipse Adoptium",
+                        "Eclipse Foundation",
+                        "AdoptOpenJDK",
+                        "Microsoft",
+                        "Zulu",
+                        "BellSoft",
+                        "Amazon Corretto",
+                        "Minecraft Launcher\\runtime",
+                    ] {
+                        search_dirs.push(program_files.join(vendor));
+                    }
+                }
+            }
+            if let Some(local) = dirs::data_local_dir() {
+                // Microsoft Store version of the launcher
+                search_dirs.push(local.join(
+                    "Packages\\Microsoft.4297127D64EC6_8wekyb3d8bbwe\\LocalCache\\Local\\runtime",
+                ));
+            }
+        }
+        "macos" => {
+            search_dirs.push(PathBuf::from("/Library/Java/JavaVirtualMachines"));
+            if let Some(home) = dirs::home_dir() {
+                search_dirs.push(home.join("Library/Java/JavaVirtualMachines"));
+            }
+        }
+        _ => {
+            for dir in [
+                "/usr/lib/jvm",
+                "/usr/lib64/jvm",
+                "/usr/java",
+                "/opt/java",
+                "/opt/jdk",
+            ] {
+                search_dirs.push(PathBuf::from(dir));
+            }
+        }
+    }
+    match launcher {
+        // Prism downloads runtimes into its data directory
+        Some(Launcher::MultiMC(root)) => search_dirs.push(root.join("java")),
+        Some(Launcher::Vanilla(root)) => search_dirs.push(root.join("runtime")),
+        _ => {}
+    }
+    search_dirs
+}
+
+/// Finds installed java runtimes, checking `JAVA_HOME`, `PATH`, common install locations and launcher bundled runtimes
+pub(crate) fn discover_java(launcher: Option<&Launcher>) -> Vec<JavaRuntime> {
+    let mut candidates = vec![];
+    if let Ok(java_home) = env::var("JAVA_HOME") {
+        candidates.extend(java_executable(&PathBuf::from(java_home).join("bin")));
+    }
+    if let Some(path) = env::var_os("PATH") {
+        candidates.extend(env::split_paths(&path).filter_map(|x| java_executable(&x)));
+    }
+    for dir in java_search_dirs(launcher) {
+        find_java_executables(&dir, 5, &mut candidates);
+    }
+    let mut seen = vec![];
+    let mut runtimes = vec![];
+    for java in candidates {
+        let canonical = java.canonicalize().unwrap_or(java.clone());
+        if seen.contains(&canonical) {
+            continue;
+        }
+        seen.push(canonical);
+        if let Some(version) = get_java_version(&java) {
+            runtimes.push(JavaRuntime {
+                path: windowless_java(java),
+                version,
+            });
+        }
+    }
+    runtimes.sort_by_key(|x| std::cmp::Reverse(x.version));
+    info!("Discovered java runtimes: {runtimes:#?}");
+    runtimes
+}
+
+/// Sets the java executable the launcher uses for the pack, `None` resets it to the launcher's default
+pub(crate) fn set_configured_java(launcher: &Launcher, uuid: &str, java: Option<&Path>) {
+    launcher.backend().set_java_path(uuid, java);
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct AdoptiumPackage {
+    name: String,
+    link: String,
+    checksum: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct AdoptiumBinary {
+    package: AdoptiumPackage,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct AdoptiumRelease {
+    binary: AdoptiumBinary,
+    release_name: String,
+}
+
+fn get_managed_java_dir() -> PathBuf {
+    get_app_data().join(".WC_OVHL/java")
+}
+
+fn adoptium_platform() -> Result<(&'static str, &'static str), String> {
+    let os = match env::consts::OS {
+        "windows" => "windows",
+        "macos" => "mac",
+        "linux" => "linux",
+        x => return Err(format!("Unsupported os '{x}'!")),
+    };
+    let arch = match env::consts::ARCH {
+        "x86_64" => "x64",
+        "x86" => "x32",
+        "aarch64" => "aarch64",
+        "arm" => "arm",
+        x => return Err(format!("Unsupported architecture '{x}'!")),
+    };
+    Ok((os, arch))
+}
+
+/// Finds a runtime of the java major version in `dir` that was downloaded before
+fn find_downloaded_java(dir: &Path, version: u32) -> Option<PathBuf> {
+    let entries = fs::read_dir(dir).ok()?;
+    entries.flatten().find_map(|entry| {
+        let path = entry.path();
+        // Unfinished extractions
+        if path.extension().is_some_and(|x| x == "tmp") {
+            return None;
+        }
+        let mut found = vec![];
+        find_java_executables(&path, 3, &mut found);
+        found
+            .into_iter()
+            .find(|x| get_java_version(x) == Some(version))
+    })
+}
+
+/// Downloads the latest Adoptium JRE for a java major version into the installer data directory and returns its java executable
+///
+/// Always uses the public Adoptium API, the base URL is only a parameter of `download_adoptium_java` so
+/// tests can point it at a local stand-in
+pub(crate) async fn download_java(
+    version: u32,
+    http_client: &CachedHttpClient,
+) -> Result<PathBuf, String> {
+    download_adoptium_java(version, ADOPTIUM_API, &get_managed_java_dir(), http_client).await
+}
+
+/// Downloads the JRE from the Adoptium API at `api` into `java_dir`, runtimes already in `java_dir` are used
+/// without asking the API
+async fn download_adoptium_java(
+    version: u32,
+    api: &str,
+    java_dir: &Path,
+    http_client: &CachedHttpClient,
+) -> Result<PathBuf, String> {
+    if let Some(java) = find_downloaded_java(java_dir, version) {
+        info!("Java {version} is already downloaded: {java:#?}");
+        return Ok(windowless_java(java));
+    }
+    let (os, arch) = adoptium_platform()?;
+    let mut resp = http_client
+        .get_nocache(format!(
+            "{api}v3/assets/latest/{version}/hotspot?architecture={arch}&image_type=jre&os={os}&vendor=eclipse"
+        ))
+        .await
+        .map_err(|e| e.to_string())?;
+    if resp.status() != StatusCode::OK {
+        return Err(format!(
+            "Encountered '{}' error code when looking up Java {version}",
+            resp.status().as_u16()
+        ));
+    }
+    let releases: Vec<AdoptiumRelease> =
+        serde_json::from_str(&resp.text().await.map_err(|e| e.to_string())?)
+            .map_err(|e| e.to_string())?;
+    let release = releases.first().ok_or(format!(
+        "No Java {version} release available for {os} {arch}"
+    ))?;
+    let package = &release.binary.package;
+    info!("Downloading Java {version}: '{}'", package.name);
+    fs::create_dir_all(java_dir).map_err(|e| e.to_string())?;
+    // Extract next to the final directory so a failed extraction doesn't leave a broken runtime behind
+    let tmp_dir = java_dir.join(format!("{}.tmp", release.release_name));
+    let _ = fs::remove_dir_all(&tmp_dir);
+    let archive_path = java_dir.join(format!("{}.tmp", package.name));
+    let result = download_archive(&package.link, &package.checksum, &archive_path, http_client)
+        .await
+        .and_then(|_| extract_archive(&package.name, &archive_path, &tmp_dir));
+    let _ = fs::remove_file(&archive_path);
+    result?;
+    let dir = java_dir.join(&release.release_name);
+    let _ = fs::remove_dir_all(&dir);
+    fs::rename(&tmp_dir, &dir).map_err(|e| e.to_string())?;
+    let mut found = vec![];
+    find_java_executables(&dir, 3, &mut found);
+    let java = found.into_iter().next().ok_or(format!(
+        "Downloaded Java {version} does not contain a java executable"
+    ))?;
+    info!("Downloaded Java {version} to {java:#?}");
+    Ok(windowless_java(java))
+}
+
+/// Streams the archive at `url` to `path`, checking its sha256 `checksum` on the way
+async fn download_archive(
+    url: &str,
+    checksum: &str,
+    path: &Path,
+    http_client: &CachedHttpClient,
+) -> Result<(), String> {
+    let mut resp = http_client
+        .get_nocache(url)
+        .await
+        .map_err(|e| e.to_string())?;
+    if resp.status() != StatusCode::OK {
+        return Err(format!(
+            "Encountered '{}' error code when downloading '{url}'",
+            resp.status().as_u16()
+        ));
+    }
+    let mut file = File::create(path).map_err(|e| e.to_string())?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0; 64 * 1024];
+    loop {
+        let n = resp
+            .body_mut()
+            .read(&mut buf)
+            .await
+            .map_err(|e| e.to_string())?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+        file.write_all(&buf[..n]).map_err(|e| e.to_string())?;
+    }
+    let actual = format!("{:x}", hasher.finalize());
+    if !actual.eq_ignore_ascii_case(checksum) {
+        return Err(format!(
+            "Checksum mismatch for '{url}', expected '{checksum}' got '{actual}'"
+        ));
+    }
+    Ok(())
+}
+
+fn extract_archive(name: &str, archive: &Path, dir: &Path) -> Result<(), String> {
+    let file = File::open(archive).map_err(|e| e.to_string())?;
+    if name.ends_with(".zip") {
+        zip::ZipArchive::new(file)
+            .and_then(|mut x| x.extract(dir))
+            .map_err(|e| e.to_string())
+    } else {
+        tar::Archive::new(GzDecoder::new(file))
+            .unpack(dir)
+            .map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::{
+        io::{BufRead, BufReader},
+        net::TcpListener,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        thread,
+    };
+
+    use flate2::{write::GzEncoder, Compression};
+    use futures::executor::block_on;
+    use serde_json::json;
+
+    use super::*;
+    use crate::launcher::tests::TempDir;
+
+    /// A JRE whose `java -version` reports `version`
+    fn jre_archive(version: u32) -> Vec<u8> {
+        let script = format!("#!/bin/sh\necho 'openjdk version \"{version}.0.1\"' >&2\n");
+        let mut header = tar::Header::new_gnu();
+        header.set_size(script.len() as u64);
+        header.set_mode(0o755);
+        header.set_cksum();
+        let mut builder = tar::Builder::new(GzEncoder::new(vec![], Compression::default()));
+        builder
+            .append_data(&mut header, "jdk-21-jre/bin/java", script.as_bytes())
+            .unwrap();
+        builder.into_inner().unwrap().finish().unwrap()
+    }
+
+    /// Serves the Adoptium API endpoint and the archive it links to, returns the api url and the number of requests
+    fn serve_adoptium(archive: Vec<u8>) -> (String, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let api = format!("http://{}/", listener.local_addr().unwrap());
+        let requests = Arc::new(AtomicUsize::new(0));
+        let release = json!([{
+            "release_name": "jdk-21.0.1+12",
+            "binary": { "package": {
+                "name": "OpenJDK21U-jre.tar.gz",
+                "link": format!("{api}jre.tar.gz"),
+                "checksum": format!("{:x}", Sha256::digest(&archive)),
+            } },
+        }])
+        .to_string();
+        let counter = requests.clone();
+        thread::spawn(move || {
+            for mut stream in listener.incoming().flatten() {
+                counter.fetch_add(1, Ordering::Relaxed);
+                let mut request_line = String::new();
+                let mut reader = BufReader::new(&stream);
+                reader.read_line(&mut request_line).unwrap();
+                // Skip the headers
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap() > 2 {
+                    line.clear();
+                }
+                let body = if request_line.contains("/v3/assets/latest/21/") {
+                    release.as_bytes().to_vec()
+                } else {
+                    archive.clone()
+                };
+                write!(
+                    stream,
+                    "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    body.len()
+                )
+                .unwrap();
+                stream.write_all(&body).unwrap();
+            }
+        });
+        (api, requests)
+    }
+
+    #[test]
+    fn download_java_uses_downloaded_runtime() {
+        let dir = TempDir::new();
+        let (api, requests) = serve_adoptium(jre_archive(21));
+        let http_client = CachedHttpClient::new();
+        let java = block_on(download_adoptium_java(21, &api, dir.path(), &http_client)).unwrap();
+        assert_eq!(java, dir.path().join("jdk-21.0.1+12/jdk-21-jre/bin/java"));
+        assert_eq!(get_java_version(&java), Some(21));
+        assert_eq!(requests.load(Ordering::Relaxed), 2);
+        // Only the extracted runtime is left behind
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+        let again = block_on(download_adoptium_java(21, &api, dir.path(), &http_client)).unwrap();
+        assert_eq!(again, java);
+        assert_eq!(requests.load(Ordering::Relaxed), 2);
+    }
+
+    #[test]
+    fn download_archive_checks_checksum() {
+        let dir = TempDir::new();
+        let (api, _) = serve_adoptium(jre_archive(21));
+        let path = dir.path().join("jre.tar.gz");
+        let result = block_on(download_archive(
+            &format!("{api}jre.tar.gz"),
+            &format!("{:x}", Sha256::digest(jre_archive(17))),
+            &path,
+            &CachedHttpClient::new(),
+        ));
+        assert!(result.unwrap_err().starts_with("Checksum mismatch"));
+    }
+}
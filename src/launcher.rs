@@ -0,0 +1,531 @@
+// Launcher specific logic, adding a launcher means adding a `LauncherBackend` here
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use async_trait::async_trait;
+use image::DynamicImage;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    get_local_manifests, InstallerProfile, LaunchError, Launcher, LauncherProfileError, Manifest,
+};
+
+mod atlauncher;
+mod gdlauncher;
+mod modrinth;
+mod multimc;
+mod vanilla;
+
+pub(crate) use atlauncher::{get_atlauncher_folder, ATLauncherBackend};
+pub(crate) use gdlauncher::{get_gdlauncher_folder, GDLauncherBackend};
+pub(crate) use modrinth::{get_modrinth_folder, ModrinthBackend};
+pub(crate) use multimc::{get_multimc_folder, MultiMCBackend};
+pub(crate) use vanilla::{
+    get_minecraft_folder, get_profile_versions, get_vanilla_data_dir, VanillaBackend,
+};
+
+/// A launcher that can be selected, `id` is the string stored in the config's `launcher` field
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct LauncherLocation {
+    pub id: String,
+    pub name: String,
+}
+
+#[async_trait]
+pub(crate) trait LauncherBackend: Send + Sync {
+    /// Every install of the launcher found on this system
+    fn detect() -> Vec<LauncherLocation>
+    where
+        Self: Sized;
+
+    /// Folder the pack's game files are installed to
+    fn game_dir(&self, uuid: &str) -> PathBuf;
+
+    /// Same as `game_dir` but creates the folder if it doesn't exist
+    fn instance_root(&self, uuid: &str) -> PathBuf {
+        let root = self.game_dir(uuid);
+        fs::create_dir_all(&root).expect("Failed to create modpack folder");
+        root
+    }
+
+    /// Folder everything the installer creates for packs is in, install records can't remove anything
+    /// outside of it
+    fn data_root(&self) -> PathBuf;
+
+    /// Folder kept saves and screenshots are moved to when the pack is uninstalled, `None` leaves them
+    /// in the game directory
+    fn kept_dir(&self, _uuid: &str) -> Option<PathBuf> {
+        None
+    }
+
+    /// The `.minecraft` folder the installer downloads the loader into, `None` for launchers that
+    /// install the loader themselves
+    fn minecraft_dir(&self) -> Option<PathBuf> {
+        None
+    }
+
+    /// Creates or updates the launcher's profile/instance for the pack
+    async fn write_profile(
+        &self,
+        installer_profile: &InstallerProfile,
+        icon_img: Option<DynamicImage>,
+    ) -> Result<(), LauncherProfileError>;
+
+    /// Every file and folder the installer creates for the pack
+    fn artifacts(&self, uuid: &str) -> Vec<PathBuf>;
+
+    /// Removes the pack's entry from the launcher's own registry, files are removed through `artifacts`
+    fn remove_profile(&self, _uuid: &str) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Manifests of every pack installed in the launcher, `Err` for manifests that failed to parse
+    fn list_installed(&self) -> io::Result<Vec<Result<Manifest, String>>>;
+
+    /// The java executable the launcher has been told to use for the pack
+    fn java_path(&self, uuid: &str) -> Option<PathBuf>;
+
+    /// Sets the java executable used for the pack, `None` resets it to the launcher's default
+    fn set_java_path(&self, uuid: &str, java: Option<&Path>);
+
+    /// Known locations of the launcher's executable, the first one that exists is used to start packs
+    fn executable_candidates(&self) -> Vec<PathBuf> {
+        vec![]
+    }
+
+    /// Prepares the launcher to start the pack and returns the arguments that start it, `None` if the
+    /// launcher can't start packs from the command line
+    fn prepare_launch(&self, _uuid: &str) -> Option<Vec<String>> {
+        None
+    }
+}
+
+impl Launcher {
+    pub(crate) fn backend(&self) -> Box<dyn LauncherBackend> {
+        match self {
+            Launcher::Vanilla(root) => Box::new(VanillaBackend {
+                root: root.clone(),
+                data_dir: get_vanilla_data_dir(root),
+            }),
+            Launcher::MultiMC(root) => Box::new(MultiMCBackend { root: root.clone() }),
+            Launcher::ATLauncher(root) => Box::new(ATLauncherBackend { root: root.clone() }),
+            Launcher::Modrinth(root) => Box::new(ModrinthBackend { root: root.clone() }),
+            Launcher::GDLauncher(root) => Box::new(GDLauncherBackend { root: root.clone() }),
+        }
+    }
+
+    /// Starts the pack through the launcher, `executable` overrides the launcher executable that was found
+    pub(crate) fn launch(&self, uuid: &str, executable: Option<&Path>) -> Result<(), LaunchError> {
+        let backend = self.backend();
+        let args = backend
+            .prepare_launch(uuid)
+            .ok_or_else(|| LaunchError::Unsupported(self.to_string()))?;
+        let executable = match executable {
+            Some(v) => v.to_path_buf(),
+            None => backend
+                .executable_candidates()
+                .into_iter()
+                .find(|x| x.is_file())
+                .ok_or_else(|| LaunchError::ExecutableNotFound(self.to_string()))?,
+        };
+        let mut command = Command::new(executable);
+        command.args(args);
+        info!("Launching '{uuid}': {command:?}");
+        command.spawn()?;
+        Ok(())
+    }
+}
+
+/// Every launcher that was found on this system, in the order they should be offered
+pub(crate) fn discover_launchers() -> Vec<LauncherLocation> {
+    let mut launchers: Vec<LauncherLocation> = vec![];
+    for location in [
+        VanillaBackend::detect(),
+        MultiMCBackend::detect(),
+        ATLauncherBackend::detect(),
+        ModrinthBackend::detect(),
+        GDLauncherBackend::detect(),
+    ]
+    .into_iter()
+    .flatten()
+    {
+        if !launchers.iter().any(|x| x.id == location.id) {
+            launchers.push(location);
+        }
+    }
+    launchers
+}
+
+/// Finds `name` in the directories of `PATH`
+fn find_in_path(name: &str) -> Option<PathBuf> {
+    let name = if env::consts::OS == "windows" {
+        format!("{name}.exe")
+    } else {
+        name.to_string()
+    };
+    env::split_paths(&env::var_os("PATH")?)
+        .map(|x| x.join(&name))
+        .find(|x| x.is_file())
+}
+
+/// Wrappers flatpak exports for the app `id`, they take the same arguments as the app itself
+fn flatpak_exports(id: &str) -> Vec<PathBuf> {
+    let mut exports = vec![];
+    if let Some(data) = dirs::data_dir() {
+        exports.push(data.join(format!("flatpak/exports/bin/{id}")));
+    }
+    exports.push(PathBuf::from(format!("/var/lib/flatpak/exports/bin/{id}")));
+    exports
+}
+
+/// Reads the manifests of `<dir>/*/<manifest>`, errors reading `dir` itself are returned
+fn list_manifests(dir: PathBuf, manifest: &str) -> io::Result<Vec<Result<Manifest, String>>> {
+    fs::read_dir(&dir)?;
+    Ok(get_local_manifests(dir, manifest))
+}
+
+/// Files and folders created by an install, stored in the pack's game directory so uninstalling
+/// removes them even if a newer installer version places things elsewhere
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct InstallRecord {
+    paths: Vec<PathBuf>,
+}
+
+pub(crate) const INSTALL_RECORD: &str = "install_record.json";
+
+fn read_install_record(backend: &dyn LauncherBackend, uuid: &str) -> Option<InstallRecord> {
+    serde_json::from_str(&fs::read_to_string(backend.game_dir(uuid).join(INSTALL_RECORD)).ok()?)
+        .ok()
+}
+
+/// Adds the backend's artifacts to the pack's install record, paths from previous installs are kept
+pub(crate) fn write_install_record(backend: &dyn LauncherBackend, uuid: &str) -> io::Result<()> {
+    let mut record = read_install_record(backend, uuid).unwrap_or_default();
+    for path in backend.artifacts(uuid) {
+        if !record.paths.contains(&path) {
+            record.paths.push(path);
+        }
+    }
+    fs::write(
+        backend.instance_root(uuid).join(INSTALL_RECORD),
+        serde_json::to_string_pretty(&record)?,
+    )
+}
+
+/// Removes everything recorded for the pack except for `keep` and the folders containing it
+pub(crate) fn remove_install(
+    backend: &dyn LauncherBackend,
+    uuid: &str,
+    keep: &[PathBuf],
+) -> io::Result<()> {
+    let paths = match read_install_record(backend, uuid) {
+        Some(record) => record.paths,
+        None => {
+            warn!("No install record found for '{uuid}', removing the default files");
+            backend.artifacts(uuid)
+        }
+    };
+    let keep = match backend.kept_dir(uuid) {
+        Some(kept_dir) => move_kept(&backend.game_dir(uuid), keep, &kept_dir),
+        None => keep.to_vec(),
+    };
+    // The removed paths are canonicalized, so the kept ones have to be as well
+    let keep: Vec<PathBuf> = keep
+        .iter()
+        .map(|x| canonicalize_link(x).unwrap_or(x.clone()))
+        .collect();
+    let root = backend.data_root().canonicalize()?;
+    for path in paths {
+        match confine(&root, &path) {
+            Some(path) => remove_path(&path, &keep)?,
+            None => warn!("Not removing {path:#?}, it is outside of {root:#?}"),
+        }
+    }
+    Ok(())
+}
+
+/// Canonicalizes the folder containing `path`, links themselves aren't resolved so they are removed
+/// instead of their target
+fn canonicalize_link(path: &Path) -> Option<PathBuf> {
+    Some(path.parent()?.canonicalize().ok()?.join(path.file_name()?))
+}
+
+/// The canonical form of `path` if it is inside `root`
+fn confine(root: &Path, path: &Path) -> Option<PathBuf> {
+    let path = canonicalize_link(path)?;
+    (path.starts_with(root) && path != root).then_some(path)
+}
+
+/// Moves the `keep` paths of `game_dir` to `kept_dir`, returns the paths that couldn't be moved and
+/// have to be kept in place
+fn move_kept(game_dir: &Path, keep: &[PathBuf], kept_dir: &Path) -> Vec<PathBuf> {
+    let mut in_place = vec![];
+    for path in keep.iter().filter(|x| x.exists()) {
+        let target = match path.strip_prefix(game_dir) {
+            Ok(v) => kept_dir.join(v),
+            Err(_) => {
+                in_place.push(path.clone());
+                continue;
+            }
+        };
+        let result = if target.exists() {
+            Err(io::Error::from(io::ErrorKind::AlreadyExists))
+        } else {
+            fs::create_dir_all(target.parent().unwrap()).and_then(|_| fs::rename(path, &target))
+        };
+        match result {
+            Ok(_) => info!("Moved {path:#?} to {target:#?}"),
+            Err(e) => {
+                warn!("Failed to move {path:#?} to {target:#?}, keeping it in place: {e}");
+                in_place.push(path.clone());
+            }
+        }
+    }
+    in_place
+}
+
+/// Moves files kept when the pack was uninstalled back into its game directory
+pub(crate) fn restore_kept(backend: &dyn LauncherBackend, uuid: &str) -> io::Result<()> {
+    let kept_dir = match backend.kept_dir(uuid) {
+        Some(v) if v.is_dir() => v,
+        _ => return Ok(()),
+    };
+    let game_dir = backend.instance_root(uuid);
+    for entry in fs::read_dir(&kept_dir)? {
+        let entry = entry?;
+        let target = game_dir.join(entry.file_name());
+        if target.exists() {
+            warn!(
+                "Not restoring {:#?}, {target:#?} already exists",
+                entry.path()
+            );
+            continue;
+        }
+        fs::rename(entry.path(), &target)?;
+        info!("Restored {target:#?}");
+    }
+    // Only succeeds if everything was restored
+    let _ = fs::remove_dir(&kept_dir);
+    Ok(())
+}
+
+fn remove_path(path: &Path, keep: &[PathBuf]) -> io::Result<()> {
+    if keep.iter().any(|x| x == path) {
+        return Ok(());
+    }
+    if path.is_dir() && !path.is_symlink() {
+        if keep.iter().any(|x| x.starts_with(path)) {
+            for entry in fs::read_dir(path)? {
+                remove_path(&entry?.path(), keep)?;
+            }
+            if fs::read_dir(path)?.next().is_some() {
+                return Ok(());
+            }
+            fs::remove_dir(path)?;
+        } else {
+            fs::remove_dir_all(path)?;
+        }
+    } else if path.symlink_metadata().is_ok() {
+        fs::remove_file(path)?;
+    } else {
+        return Ok(());
+    }
+    info!("Removed: {path:#?}");
+    Ok(())
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::{
+        env, fs,
+        path::{Path, PathBuf},
+        process,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    use super::*;
+    use crate::CachedHttpClient;
+
+    /// A folder in the system's temp dir that is removed when dropped
+    pub(crate) struct TempDir(PathBuf);
+
+    impl TempDir {
+        pub(crate) fn new() -> TempDir {
+            static COUNTER: AtomicUsize = AtomicUsize::new(0);
+            let path = env::temp_dir().join(format!(
+                "wc-ovhl-test-{}-{}",
+                process::id(),
+                COUNTER.fetch_add(1, Ordering::Relaxed)
+            ));
+            fs::create_dir_all(&path).unwrap();
+            TempDir(path)
+        }
+
+        pub(crate) fn path(&self) -> &Path {
+            &self.0
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    pub(crate) const UUID: &str = "5b1a6f86-5bc5-4c36-8bd4-8b7c0a0f0c21";
+
+    pub(crate) fn test_manifest() -> Manifest {
+        serde_json::from_value(serde_json::json!({
+            "manifest_version": 4,
+            "modpack_version": "1.0.0",
+            "name": "Test Pack",
+            "subtitle": "Release",
+            "description": "",
+            "icon": false,
+            "uuid": UUID,
+            "loader": {
+                "type": "fabric",
+                "version": "0.15.11",
+                "minecraft_version": "1.20.1",
+            },
+            "mods": [],
+            "shaderpacks": [],
+            "resourcepacks": [],
+            "include": [],
+            "features": [],
+            "max_mem": 4096,
+            "min_mem": 2048,
+            "java_args": "-XX:+UseG1GC",
+        }))
+        .unwrap()
+    }
+
+    pub(crate) fn test_profile() -> InstallerProfile {
+        InstallerProfile {
+            manifest: test_manifest(),
+            http_client: CachedHttpClient::new(),
+            installed: false,
+            update_available: false,
+            modpack_source: String::new(),
+            modpack_branch: String::new(),
+            enabled_features: vec![],
+            launcher: None,
+            local_manifest: None,
+            java_warning: None,
+            feature_notices: vec![],
+            java_path: None,
+            managed_java: false,
+            instance_name: None,
+        }
+    }
+
+    /// Stores the manifest in the pack's game directory the way an install does
+    pub(crate) fn write_local_manifest(game_dir: &Path, manifest: &Manifest) {
+        fs::create_dir_all(game_dir).unwrap();
+        fs::write(
+            game_dir.join("manifest.json"),
+            serde_json::to_string(manifest).unwrap(),
+        )
+        .unwrap();
+    }
+
+    fn create_file(path: &Path) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, "").unwrap();
+    }
+
+    #[test]
+    fn list_installed_reads_game_dirs() {
+        let manifest = test_manifest();
+        for launcher in [
+            Launcher::Vanilla,
+            Launcher::MultiMC,
+            Launcher::ATLauncher,
+            Launcher::Modrinth,
+            Launcher::GDLauncher,
+        ] {
+            let dir = TempDir::new();
+            let backend = launcher(dir.path().to_path_buf()).backend();
+            assert!(backend.list_installed().is_err());
+            write_local_manifest(&backend.game_dir(UUID), &manifest);
+            // Folders the installer didn't create aren't packs
+            fs::create_dir_all(backend.game_dir("other")).unwrap();
+            assert_eq!(
+                backend.list_installed().unwrap(),
+                vec![Ok(manifest.clone())]
+            );
+        }
+    }
+
+    #[test]
+    fn remove_install_refuses_paths_outside_root() {
+        let dir = TempDir::new();
+        let outside = TempDir::new();
+        let backend = MultiMCBackend {
+            root: dir.path().to_path_buf(),
+        };
+        let victim = outside.path().join("victim.txt");
+        create_file(&victim);
+        create_file(&backend.game_dir(UUID).join("options.txt"));
+        let record = InstallRecord {
+            paths: vec![
+                victim.clone(),
+                dir.path()
+                    .join("instances/../..")
+                    .join(outside.path().file_name().unwrap()),
+                dir.path().to_path_buf(),
+                dir.path().join(format!("instances/{UUID}")),
+            ],
+        };
+        fs::write(
+            backend.game_dir(UUID).join(INSTALL_RECORD),
+            serde_json::to_string(&record).unwrap(),
+        )
+        .unwrap();
+        remove_install(&backend, UUID, &[]).unwrap();
+        assert!(victim.is_file());
+        assert!(dir.path().is_dir());
+        assert!(!dir.path().join(format!("instances/{UUID}")).exists());
+    }
+
+    #[test]
+    fn remove_install_moves_kept_files_out_of_instance() {
+        let dir = TempDir::new();
+        let backend = MultiMCBackend {
+            root: dir.path().to_path_buf(),
+        };
+        let game_dir = backend.game_dir(UUID);
+        create_file(&dir.path().join(format!("instances/{UUID}/instance.cfg")));
+        create_file(&game_dir.join("saves/world/level.dat"));
+        create_file(&game_dir.join("mods/mod.jar"));
+        write_install_record(&backend, UUID).unwrap();
+        remove_install(&backend, UUID, &[game_dir.join("saves")]).unwrap();
+        assert!(!dir.path().join(format!("instances/{UUID}")).exists());
+        let kept_dir = backend.kept_dir(UUID).unwrap();
+        assert!(kept_dir.join("saves/world/level.dat").is_file());
+        restore_kept(&backend, UUID).unwrap();
+        assert!(game_dir.join("saves/world/level.dat").is_file());
+        assert!(!kept_dir.exists());
+    }
+
+    #[test]
+    fn remove_install_keeps_files_in_place() {
+        let dir = TempDir::new();
+        let backend = VanillaBackend {
+            root: dir.path().join(".minecraft"),
+            data_dir: dir.path().to_path_buf(),
+        };
+        let game_dir = backend.game_dir(UUID);
+        create_file(&game_dir.join("saves/world/level.dat"));
+        create_file(&game_dir.join("mods/mod.jar"));
+        write_install_record(&backend, UUID).unwrap();
+        remove_install(&backend, UUID, &[game_dir.join("saves")]).unwrap();
+        assert!(game_dir.join("saves/world/level.dat").is_file());
+        assert!(!game_dir.join("mods").exists());
+        assert!(!game_dir.join(INSTALL_RECORD).exists());
+    }
+}
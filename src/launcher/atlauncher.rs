@@ -0,0 +1,385 @@
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+};
+
+use async_trait::async_trait;
+use image::DynamicImage;
+use log::{error, info};
+use serde_json::{json, Value as JsonValue};
+
+use super::{find_in_path, flatpak_exports, list_manifests, LauncherBackend, LauncherLocation};
+use crate::{
+    get_app_data, java, CachedHttpClient, InstallerProfile, LauncherProfileError, LoaderLibrary,
+    Manifest,
+};
+
+pub(crate) fn get_atlauncher_folder() -> Result<PathBuf, String> {
+    let path = match env::consts::OS {
+        "linux" => get_app_data().join(".local/share/atlauncher"),
+        "windows" | "macos" => get_app_data().join("ATLauncher"),
+        _ => panic!("Unsupported os '{}'!", env::consts::OS),
+    };
+    if path.join("instances").is_dir() {
+        Ok(path)
+    } else {
+        Err(String::from(
+            "ATLauncher directory is not a valid directory!",
+        ))
+    }
+}
+
+/// Converts a loader library into the Mojang format ATLauncher expects
+fn convert_library(library: &LoaderLibrary) -> Option<JsonValue> {
+    let path = library.path()?;
+    let mut artifact = json!({
+        "path": path,
+        "url": format!(
+            "{}{}",
+            library
+                .url
+                .as_deref()
+                .unwrap_or("https://libraries.minecraft.net/"),
+            path
+        ),
+    });
+    if let Some(sha1) = &library.sha1 {
+        artifact["sha1"] = json!(sha1);
+    }
+    if let Some(size) = library.size {
+        artifact["size"] = json!(size);
+    }
+    Some(json!({
+        "name": library.name,
+        "downloads": { "artifact": artifact },
+    }))
+}
+
+fn append_array(target: &mut JsonValue, key: &str, values: Option<&JsonValue>) {
+    if let Some(JsonValue::Array(values)) = values {
+        match target.get_mut(key) {
+            Some(JsonValue::Array(existing)) => existing.extend(values.iter().cloned()),
+            _ => target[key] = JsonValue::Array(values.clone()),
+        }
+    }
+}
+
+/// The full version json merged with the loader profile, which ATLauncher expects `instance.json` to contain
+async fn get_version_json(
+    installer_profile: &InstallerProfile,
+) -> Result<JsonValue, LauncherProfileError> {
+    let manifest = &installer_profile.manifest;
+    let http_client: &CachedHttpClient = &installer_profile.http_client;
+    let mut instance =
+        java::get_minecraft_version_json(&manifest.loader.minecraft_version, http_client)
+            .await
+            .map_err(LauncherProfileError::InvalidMetadata)?;
+    let loader_profile = manifest
+        .loader
+        .get_profile_json(http_client)
+        .await
+        .map_err(LauncherProfileError::InvalidMetadata)?;
+    let loader_libraries: Vec<LoaderLibrary> = serde_json::from_value(
+        loader_profile
+            .get("libraries")
+            .cloned()
+            .unwrap_or(JsonValue::Array(vec![])),
+    )?;
+    let loader_libraries: Vec<JsonValue> = loader_libraries
+        .iter()
+        .filter_map(convert_library)
+        .collect();
+    append_array(
+        &mut instance,
+        "libraries",
+        Some(&JsonValue::Array(loader_libraries)),
+    );
+    if let Some(main_class) = loader_profile.get("mainClass") {
+        instance["mainClass"] = main_class.clone();
+    }
+    if let Some(arguments) = loader_profile.get("arguments") {
+        if instance.get("arguments").is_none() {
+            instance["arguments"] = json!({});
+        }
+        append_array(&mut instance["arguments"], "game", arguments.get("game"));
+        append_array(&mut instance["arguments"], "jvm", arguments.get("jvm"));
+    }
+    Ok(instance)
+}
+
+/// Writes `instance.json` with the pack's settings added to the version json
+fn create_instance(
+    instance_dir: &Path,
+    installer_profile: &InstallerProfile,
+    mut instance: JsonValue,
+) -> Result<(), LauncherProfileError> {
+    let manifest = &installer_profile.manifest;
+    let instance_json_path = instance_dir.join("instance.json");
+    // Keep settings the user changed in ATLauncher
+    let mut launcher = fs::read_to_string(&instance_json_path)
+        .ok()
+        .and_then(|x| serde_json::from_str::<JsonValue>(&x).ok())
+        .and_then(|x| x.get("launcher").cloned())
+        .filter(|x| x.is_object())
+        .unwrap_or(json!({
+            "isDev": false,
+            "enableCurseForgeIntegration": false,
+            "enableEditingModpackComponents": false,
+            "requiredMemory": 0,
+            "requiredPermGen": 0,
+            "mods": [],
+        }));
+    launcher["name"] = json!(installer_profile.instance_name());
+    launcher["pack"] = json!(manifest.name);
+    launcher["version"] = json!(manifest.modpack_version);
+    launcher["loaderVersion"] = json!({
+        "version": manifest.loader.version,
+        "rawVersion": manifest.loader.version,
+        "recommended": false,
+        "type": match manifest.loader.r#type.as_str() {
+            "fabric" => "Fabric",
+            "quilt" => "Quilt",
+            _ => panic!("Invalid loader"),
+        },
+    });
+    if let Some(max_mem) = manifest.max_mem {
+        launcher["maximumMemory"] = json!(max_mem);
+    }
+    if let Some(min_mem) = manifest.min_mem {
+        launcher["initialMemory"] = json!(min_mem);
+    }
+    if let Some(java_args) = installer_profile.java_args() {
+        launcher["javaArguments"] = json!(java_args);
+    }
+    if let Some(java) = &installer_profile.java_path {
+        launcher["javaPath"] = json!(java.to_string_lossy());
+    }
+    instance["launcher"] = launcher;
+    info!("Writing ATLauncher instance: {instance_json_path:#?}");
+    fs::write(instance_json_path, serde_json::to_string_pretty(&instance)?)?;
+    Ok(())
+}
+
+fn read_instance(instance_json: &Path) -> Option<JsonValue> {
+    serde_json::from_str(&fs::read_to_string(instance_json).ok()?).ok()
+}
+
+/// ATLauncher, packs are installed as `<root>/instances/<uuid>` with the version and settings in `instance.json`
+pub(crate) struct ATLauncherBackend {
+    pub root: PathBuf,
+}
+
+#[async_trait]
+impl LauncherBackend for ATLauncherBackend {
+    fn detect() -> Vec<LauncherLocation> {
+        match get_atlauncher_folder() {
+            Ok(_) => vec![LauncherLocation {
+                id: String::from("atlauncher"),
+                name: String::from("ATLauncher"),
+            }],
+            Err(_) => vec![],
+        }
+    }
+
+    fn data_root(&self) -> PathBuf {
+        self.root.clone()
+    }
+
+    fn game_dir(&self, uuid: &str) -> PathBuf {
+        self.root.join(format!("instances/{uuid}"))
+    }
+
+    async fn write_profile(
+        &self,
+        installer_profile: &InstallerProfile,
+        icon_img: Option<DynamicImage>,
+    ) -> Result<(), LauncherProfileError> {
+        let instance_dir = self.instance_root(&installer_profile.manifest.uuid);
+        let instance = get_version_json(installer_profile).await?;
+        create_instance(&instance_dir, installer_profile, instance)?;
+        if installer_profile.manifest.icon {
+            icon_img
+                .ok_or(LauncherProfileError::IconNotFound)?
+                .save(instance_dir.join("instance.png"))?;
+        }
+        Ok(())
+    }
+
+    fn artifacts(&self, uuid: &str) -> Vec<PathBuf> {
+        // The instance only consists of the game directory
+        vec![self.game_dir(uuid)]
+    }
+
+    fn list_installed(&self) -> io::Result<Vec<Result<Manifest, String>>> {
+        list_manifests(self.root.join("instances/"), "manifest.json")
+    }
+
+    fn java_path(&self, uuid: &str) -> Option<PathBuf> {
+        read_instance(&self.game_dir(uuid).join("instance.json"))?
+            .get("launcher")?
+            .get("javaPath")?
+            .as_str()
+            .map(PathBuf::from)
+    }
+
+    fn set_java_path(&self, uuid: &str, java: Option<&Path>) {
+        let instance_json = self.game_dir(uuid).join("instance.json");
+        let mut instance = match read_instance(&instance_json) {
+            Some(v) => v,
+            None => return,
+        };
+        let launcher = match instance.get_mut("launcher").and_then(|x| x.as_object_mut()) {
+            Some(v) => v,
+            None => return,
+        };
+        match java {
+            Some(java) => launcher.insert(
+                String::from("javaPath"),
+                JsonValue::String(java.to_string_lossy().to_string()),
+            ),
+            None => launcher.remove("javaPath"),
+        };
+        if let Err(e) = fs::write(&instance_json, instance.to_string()) {
+            error!("Failed to write 'instance.json': {e}");
+        }
+    }
+
+    fn executable_candidates(&self) -> Vec<PathBuf> {
+        let mut candidates = vec![self.root.join("ATLauncher.exe")];
+        match env::consts::OS {
+            "linux" => {
+                candidates.extend(find_in_path("atlauncher"));
+                candidates.extend(flatpak_exports("com.atlauncher.ATLauncher"));
+            }
+            "macos" => {
+                candidates.push(PathBuf::from(
+                    "/Applications/ATLauncher.app/Contents/MacOS/ATLauncher",
+                ));
+            }
+            _ => {}
+        }
+        candidates
+    }
+
+    /// ATLauncher finds the instance to start by its name
+    fn prepare_launch(&self, uuid: &str) -> Option<Vec<String>> {
+        let name = read_instance(&self.game_dir(uuid).join("instance.json"))
+            .and_then(|x| x["launcher"]["name"].as_str().map(String::from))
+            .unwrap_or_else(|| uuid.to_string());
+        Some(vec![
+            String::from("--working-dir"),
+            self.root.to_string_lossy().to_string(),
+            String::from("--launch"),
+            name,
+        ])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::launcher::tests::{test_profile, TempDir, UUID};
+
+    fn backend(dir: &TempDir) -> ATLauncherBackend {
+        fs::create_dir_all(dir.path().join("instances")).unwrap();
+        ATLauncherBackend {
+            root: dir.path().to_path_buf(),
+        }
+    }
+
+    #[test]
+    fn write_profile_creates_instance() {
+        let dir = TempDir::new();
+        let backend = backend(&dir);
+        let instance_dir = backend.instance_root(UUID);
+        create_instance(&instance_dir, &test_profile(), json!({ "id": "1.20.1" })).unwrap();
+        let instance = read_instance(&instance_dir.join("instance.json")).unwrap();
+        assert_eq!(instance["id"], "1.20.1");
+        assert_eq!(instance["launcher"]["name"], "Test Pack (Release)");
+        assert_eq!(instance["launcher"]["loaderVersion"]["type"], "Fabric");
+        assert_eq!(instance["launcher"]["maximumMemory"], 4096);
+        assert_eq!(instance["launcher"]["initialMemory"], 2048);
+        assert_eq!(instance["launcher"]["javaArguments"], "-XX:+UseG1GC");
+    }
+
+    #[test]
+    fn write_profile_keeps_launcher_settings() {
+        let dir = TempDir::new();
+        let backend = backend(&dir);
+        let instance_dir = backend.instance_root(UUID);
+        create_instance(&instance_dir, &test_profile(), json!({})).unwrap();
+        backend.set_java_path(UUID, Some(Path::new("/usr/bin/java")));
+        let instance_json = instance_dir.join("instance.json");
+        let mut instance = read_instance(&instance_json).unwrap();
+        instance["launcher"]["enableGameLogging"] = json!(true);
+        fs::write(&instance_json, instance.to_string()).unwrap();
+        let mut installer_profile = test_profile();
+        installer_profile.manifest.modpack_version = String::from("1.1.0");
+        create_instance(&instance_dir, &installer_profile, json!({})).unwrap();
+        let launcher = &read_instance(&instance_json).unwrap()["launcher"];
+        assert_eq!(launcher["enableGameLogging"], true);
+        assert_eq!(launcher["version"], "1.1.0");
+        assert_eq!(
+            backend.java_path(UUID),
+            Some(PathBuf::from("/usr/bin/java"))
+        );
+    }
+
+    #[test]
+    fn convert_library_uses_mojang_format() {
+        let library = |url: Option<&str>, sha1: Option<&str>| LoaderLibrary {
+            name: String::from("net.fabricmc:fabric-loader:0.15.11"),
+            url: url.map(String::from),
+            sha1: sha1.map(String::from),
+            size: None,
+        };
+        let path = "net/fabricmc/fabric-loader/0.15.11/fabric-loader-0.15.11.jar";
+        assert_eq!(
+            convert_library(&library(Some("https://maven.fabricmc.net/"), Some("abc"))).unwrap(),
+            json!({
+                "name": "net.fabricmc:fabric-loader:0.15.11",
+                "downloads": { "artifact": {
+                    "path": path,
+                    "url": format!("https://maven.fabricmc.net/{path}"),
+                    "sha1": "abc",
+                } },
+            })
+        );
+        // Libraries without a repository are on Mojang's
+        assert_eq!(
+            convert_library(&library(None, None)).unwrap()["downloads"]["artifact"]["url"],
+            format!("https://libraries.minecraft.net/{path}")
+        );
+    }
+
+    #[test]
+    fn append_array_merges_arguments() {
+        let mut instance = json!({ "game": ["--username"] });
+        append_array(&mut instance, "game", Some(&json!(["--demo"])));
+        append_array(&mut instance, "jvm", Some(&json!(["-Dfoo"])));
+        append_array(&mut instance, "other", None);
+        assert_eq!(
+            instance,
+            json!({ "game": ["--username", "--demo"], "jvm": ["-Dfoo"] })
+        );
+    }
+
+    #[test]
+    fn prepare_launch_uses_instance_name() {
+        let dir = TempDir::new();
+        let backend = backend(&dir);
+        assert_eq!(backend.prepare_launch(UUID).unwrap()[3], UUID);
+        let mut installer_profile = test_profile();
+        installer_profile.instance_name = Some(String::from("My Pack"));
+        create_instance(&backend.instance_root(UUID), &installer_profile, json!({})).unwrap();
+        assert_eq!(
+            backend.prepare_launch(UUID).unwrap(),
+            [
+                "--working-dir",
+                dir.path().to_str().unwrap(),
+                "--launch",
+                "My Pack"
+            ]
+        );
+    }
+}
@@ -0,0 +1,277 @@
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+};
+
+use async_trait::async_trait;
+use image::DynamicImage;
+use log::{error, info};
+use serde_json::{json, Map, Value as JsonValue};
+
+use super::{list_manifests, LauncherBackend, LauncherLocation};
+use crate::{get_app_data, InstallerProfile, LauncherProfileError, Manifest};
+
+pub(crate) fn get_gdlauncher_folder() -> Result<PathBuf, String> {
+    let path = match env::consts::OS {
+        "linux" => get_app_data().join(".config/gdlauncher_next"),
+        "windows" | "macos" => get_app_data().join("gdlauncher_next"),
+        _ => panic!("Unsupported os '{}'!", env::consts::OS),
+    };
+    if path.join("instances").is_dir() {
+        Ok(path)
+    } else {
+        Err(String::from(
+            "GDLauncher directory is not a valid directory!",
+        ))
+    }
+}
+
+fn read_config(instance_dir: &Path) -> Option<JsonValue> {
+    serde_json::from_str::<JsonValue>(&fs::read_to_string(instance_dir.join("config.json")).ok()?)
+        .ok()
+        .filter(|x| x.is_object())
+}
+
+/// Sets keys the installer manages in `config`, keys the user changed since the last install are left
+/// alone
+///
+/// The values that were written are stored in `record_path`, without a record every key is assumed to
+/// be unchanged
+fn set_managed_values(
+    config: &mut Map<String, JsonValue>,
+    record_path: &Path,
+    values: &[(&str, Option<JsonValue>)],
+) -> io::Result<()> {
+    let record: Option<Map<String, JsonValue>> = fs::read_to_string(record_path)
+        .ok()
+        .and_then(|x| serde_json::from_str(&x).ok());
+    let mut new_record = Map::new();
+    for (key, value) in values {
+        if let Some(record) = &record {
+            if config.get(*key) != record.get(*key) {
+                info!("Keeping user customized '{key}' of the GDLauncher instance");
+                if let Some(v) = record.get(*key) {
+                    new_record.insert(key.to_string(), v.clone());
+                }
+                continue;
+            }
+        }
+        match value {
+            Some(v) => {
+                config.insert(key.to_string(), v.clone());
+                new_record.insert(key.to_string(), v.clone());
+            }
+            None => {
+                config.remove(*key);
+            }
+        }
+    }
+    fs::write(record_path, serde_json::to_string(&new_record)?)
+}
+
+/// GDLauncher, packs are installed as `<root>/instances/<uuid>` with the instance settings in `config.json`
+pub(crate) struct GDLauncherBackend {
+    pub root: PathBuf,
+}
+
+#[async_trait]
+impl LauncherBackend for GDLauncherBackend {
+    fn detect() -> Vec<LauncherLocation> {
+        match get_gdlauncher_folder() {
+            Ok(_) => vec![LauncherLocation {
+                id: String::from("gdlauncher"),
+                name: String::from("GDLauncher"),
+            }],
+            Err(_) => vec![],
+        }
+    }
+
+    fn data_root(&self) -> PathBuf {
+        self.root.clone()
+    }
+
+    fn game_dir(&self, uuid: &str) -> PathBuf {
+        self.root.join(format!("instances/{uuid}"))
+    }
+
+    /// Writes `config.json`, anything GDLauncher stored in an existing config (play time, mod list, ...)
+    /// and memory or java arguments the user changed are kept
+    async fn write_profile(
+        &self,
+        installer_profile: &InstallerProfile,
+        icon_img: Option<DynamicImage>,
+    ) -> Result<(), LauncherProfileError> {
+        let manifest = &installer_profile.manifest;
+        let instance_dir = self.instance_root(&manifest.uuid);
+        let mut config = read_config(&instance_dir).unwrap_or(json!({
+            "timePlayed": 0,
+            "mods": [],
+        }));
+        config["loader"] = json!({
+            "loaderType": manifest.loader.r#type,
+            "loaderVersion": manifest.loader.version,
+            "mcVersion": manifest.loader.minecraft_version,
+            "fileID": null,
+            "projectID": null,
+            "source": null,
+        });
+        // GDLauncher has no setting for the initial heap size, so it is passed as a java argument
+        let java_args: Vec<String> = installer_profile
+            .java_args()
+            .cloned()
+            .into_iter()
+            .chain(manifest.min_mem.map(|x| format!("-Xms{x}M")))
+            .collect();
+        set_managed_values(
+            config.as_object_mut().unwrap(),
+            &instance_dir.join("installer_managed.json"),
+            &[
+                ("javaMemory", manifest.max_mem.map(|x| json!(x))),
+                (
+                    "javaArgs",
+                    (!java_args.is_empty()).then(|| json!(java_args.join(" "))),
+                ),
+            ],
+        )?;
+        if manifest.icon {
+            icon_img
+                .ok_or(LauncherProfileError::IconNotFound)?
+                .save(instance_dir.join("icon.png"))?;
+            config["background"] = json!("icon.png");
+        }
+        if let Some(java) = &installer_profile.java_path {
+            config["customJavaPath"] = json!(java.to_string_lossy());
+        }
+        let config_path = instance_dir.join("config.json");
+        info!("Writing GDLauncher instance: {config_path:#?}");
+        fs::write(config_path, serde_json::to_string_pretty(&config)?)?;
+        Ok(())
+    }
+
+    fn artifacts(&self, uuid: &str) -> Vec<PathBuf> {
+        // The instance only consists of the game directory
+        vec![self.game_dir(uuid)]
+    }
+
+    fn list_installed(&self) -> io::Result<Vec<Result<Manifest, String>>> {
+        list_manifests(self.root.join("instances/"), "manifest.json")
+    }
+
+    fn java_path(&self, uuid: &str) -> Option<PathBuf> {
+        read_config(&self.game_dir(uuid))?
+            .get("customJavaPath")?
+            .as_str()
+            .map(PathBuf::from)
+    }
+
+    fn set_java_path(&self, uuid: &str, java: Option<&Path>) {
+        let instance_dir = self.game_dir(uuid);
+        let mut config = match read_config(&instance_dir) {
+            Some(v) => v,
+            None => return,
+        };
+        let config_obj = config.as_object_mut().unwrap();
+        match java {
+            Some(java) => config_obj.insert(
+                String::from("customJavaPath"),
+                JsonValue::String(java.to_string_lossy().to_string()),
+            ),
+            None => config_obj.remove("customJavaPath"),
+        };
+        let contents = serde_json::to_string_pretty(&config).unwrap();
+        if let Err(e) = fs::write(instance_dir.join("config.json"), contents) {
+            error!("Failed to write GDLauncher 'config.json': {e}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::executor::block_on;
+
+    use super::*;
+    use crate::launcher::tests::{test_profile, TempDir, UUID};
+
+    fn backend(dir: &TempDir) -> GDLauncherBackend {
+        fs::create_dir_all(dir.path().join("instances")).unwrap();
+        GDLauncherBackend {
+            root: dir.path().to_path_buf(),
+        }
+    }
+
+    #[test]
+    fn write_profile_creates_config() {
+        let dir = TempDir::new();
+        let backend = backend(&dir);
+        block_on(backend.write_profile(&test_profile(), None)).unwrap();
+        let config = read_config(&backend.game_dir(UUID)).unwrap();
+        assert_eq!(config["loader"]["loaderType"], "fabric");
+        assert_eq!(config["loader"]["mcVersion"], "1.20.1");
+        assert_eq!(config["javaMemory"], 4096);
+        assert_eq!(config["javaArgs"], "-XX:+UseG1GC -Xms2048M");
+    }
+
+    #[test]
+    fn write_profile_keeps_play_time() {
+        let dir = TempDir::new();
+        let backend = backend(&dir);
+        block_on(backend.write_profile(&test_profile(), None)).unwrap();
+        let config_path = backend.game_dir(UUID).join("config.json");
+        let mut config = read_config(&backend.game_dir(UUID)).unwrap();
+        config["timePlayed"] = json!(120);
+        fs::write(&config_path, config.to_string()).unwrap();
+        block_on(backend.write_profile(&test_profile(), None)).unwrap();
+        assert_eq!(
+            read_config(&backend.game_dir(UUID)).unwrap()["timePlayed"],
+            120
+        );
+    }
+
+    #[test]
+    fn write_profile_keeps_user_changes() {
+        let dir = TempDir::new();
+        let backend = backend(&dir);
+        block_on(backend.write_profile(&test_profile(), None)).unwrap();
+        let config_path = backend.game_dir(UUID).join("config.json");
+        let mut config = read_config(&backend.game_dir(UUID)).unwrap();
+        config["javaMemory"] = json!(6144);
+        fs::write(&config_path, config.to_string()).unwrap();
+        let mut installer_profile = test_profile();
+        installer_profile.manifest.max_mem = Some(8192);
+        installer_profile.manifest.java_args = Some(String::from("-XX:+UseZGC"));
+        block_on(backend.write_profile(&installer_profile, None)).unwrap();
+        let config = read_config(&backend.game_dir(UUID)).unwrap();
+        assert_eq!(config["javaMemory"], 6144);
+        assert_eq!(config["javaArgs"], "-XX:+UseZGC -Xms2048M");
+        // Once the user's value is changed back the installer manages it again
+        let mut config = read_config(&backend.game_dir(UUID)).unwrap();
+        config["javaMemory"] = json!(4096);
+        fs::write(&config_path, config.to_string()).unwrap();
+        block_on(backend.write_profile(&installer_profile, None)).unwrap();
+        assert_eq!(
+            read_config(&backend.game_dir(UUID)).unwrap()["javaMemory"],
+            8192
+        );
+        // `set_java_path` writes the same format as an install
+        backend.set_java_path(UUID, Some(Path::new("/usr/bin/java")));
+        let contents = fs::read_to_string(&config_path).unwrap();
+        let config: JsonValue = serde_json::from_str(&contents).unwrap();
+        assert_eq!(contents, serde_json::to_string_pretty(&config).unwrap());
+    }
+
+    #[test]
+    fn write_profile_sets_icon_as_background() {
+        let dir = TempDir::new();
+        let backend = backend(&dir);
+        let mut installer_profile = test_profile();
+        installer_profile.manifest.icon = true;
+        assert!(block_on(backend.write_profile(&installer_profile, None)).is_err());
+        block_on(backend.write_profile(&installer_profile, Some(DynamicImage::new_rgb8(1, 1))))
+            .unwrap();
+        assert!(backend.game_dir(UUID).join("icon.png").is_file());
+        assert_eq!(
+            read_config(&backend.game_dir(UUID)).unwrap()["background"],
+            "icon.png"
+        );
+    }
+}
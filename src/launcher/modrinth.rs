@@ -0,0 +1,310 @@
+use std::{
+    env, io,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use async_trait::async_trait;
+use image::DynamicImage;
+use log::{error, info};
+use rusqlite::{params, Connection, OptionalExtension};
+
+use super::{list_manifests, LauncherBackend, LauncherLocation};
+use crate::{get_app_data, InstallerProfile, LauncherProfileError, Manifest};
+
+pub(crate) fn get_modrinth_folder() -> Result<PathBuf, String> {
+    let path = match env::consts::OS {
+        "linux" => get_app_data().join(".local/share/ModrinthApp"),
+        "windows" | "macos" => get_app_data().join("ModrinthApp"),
+        _ => panic!("Unsupported os '{}'!", env::consts::OS),
+    };
+    if path.join("app.db").is_file() && path.join("profiles").is_dir() {
+        Ok(path)
+    } else {
+        Err(String::from(
+            "Modrinth App directory is not a valid directory!",
+        ))
+    }
+}
+
+fn open_database(root: &Path) -> rusqlite::Result<Connection> {
+    Connection::open(root.join("app.db"))
+}
+
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|x| x.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+/// Creates or updates the profile row for the pack, the profile's path is the pack's uuid
+fn create_profile(
+    root: &Path,
+    installer_profile: &InstallerProfile,
+    icon_path: Option<&Path>,
+) -> rusqlite::Result<()> {
+    let manifest = &installer_profile.manifest;
+    let db = open_database(root)?;
+    let java = installer_profile
+        .java_path
+        .as_ref()
+        .map(|x| x.to_string_lossy().to_string());
+    let java_args = installer_profile
+        .java_args()
+        .map(|x| serde_json::to_string(&x.split_whitespace().collect::<Vec<&str>>()).unwrap());
+    let icon = icon_path.map(|x| x.to_string_lossy().to_string());
+    let name = installer_profile.instance_name();
+    // Overrides the pack doesn't set are left to the user
+    let updated = db.execute(
+        "UPDATE profiles SET install_stage = 'installed', name = ?2, icon_path = ?3, game_version = ?4,
+            mod_loader = ?5, mod_loader_version = ?6, modified = ?7,
+            override_java_path = COALESCE(?8, override_java_path),
+            override_extra_launch_args = COALESCE(?9, override_extra_launch_args),
+            override_mc_memory_max = COALESCE(?10, override_mc_memory_max)
+        WHERE path = ?1",
+        params![
+            manifest.uuid,
+            name,
+            icon,
+            manifest.loader.minecraft_version,
+            manifest.loader.r#type,
+            manifest.loader.version,
+            now(),
+            java,
+            java_args,
+            manifest.max_mem,
+        ],
+    )?;
+    if updated == 0 {
+        info!("Creating Modrinth App profile: '{}'", manifest.uuid);
+        db.execute(
+            "INSERT INTO profiles (path, install_stage, name, icon_path, game_version, mod_loader,
+                mod_loader_version, groups, created, modified, override_java_path,
+                override_extra_launch_args, override_custom_env_vars, override_mc_memory_max)
+            VALUES (?1, 'installed', ?2, ?3, ?4, ?5, ?6, '[]', ?7, ?7, ?8, COALESCE(?9, '[]'), '[]', ?10)",
+            params![
+                manifest.uuid,
+                name,
+                icon,
+                manifest.loader.minecraft_version,
+                manifest.loader.r#type,
+                manifest.loader.version,
+                now(),
+                java,
+                java_args,
+                manifest.max_mem,
+            ],
+        )?;
+    }
+    Ok(())
+}
+
+fn remove_profile(root: &Path, uuid: &str) -> rusqlite::Result<()> {
+    open_database(root)?.execute("DELETE FROM profiles WHERE path = ?1", params![uuid])?;
+    Ok(())
+}
+
+fn get_java_path(root: &Path, uuid: &str) -> Option<PathBuf> {
+    open_database(root)
+        .ok()?
+        .query_row(
+            "SELECT override_java_path FROM profiles WHERE path = ?1",
+            params![uuid],
+            |row| row.get::<_, Option<String>>(0),
+        )
+        .optional()
+        .ok()??
+        .map(PathBuf::from)
+}
+
+fn set_java_path(root: &Path, uuid: &str, java: Option<&str>) -> rusqlite::Result<()> {
+    open_database(root)?.execute(
+        "UPDATE profiles SET override_java_path = ?2 WHERE path = ?1",
+        params![uuid, java],
+    )?;
+    Ok(())
+}
+
+/// The Modrinth App, packs are installed as `<root>/profiles/<uuid>` and registered in its `app.db`
+pub(crate) struct ModrinthBackend {
+    pub root: PathBuf,
+}
+
+#[async_trait]
+impl LauncherBackend for ModrinthBackend {
+    fn detect() -> Vec<LauncherLocation> {
+        match get_modrinth_folder() {
+            Ok(_) => vec![LauncherLocation {
+                id: String::from("modrinth"),
+                name: String::from("Modrinth App"),
+            }],
+            Err(_) => vec![],
+        }
+    }
+
+    fn data_root(&self) -> PathBuf {
+        self.root.clone()
+    }
+
+    fn game_dir(&self, uuid: &str) -> PathBuf {
+        self.root.join(format!("profiles/{uuid}"))
+    }
+
+    async fn write_profile(
+        &self,
+        installer_profile: &InstallerProfile,
+        icon_img: Option<DynamicImage>,
+    ) -> Result<(), LauncherProfileError> {
+        let manifest = &installer_profile.manifest;
+        let icon_path = if manifest.icon {
+            let icon_path = self.instance_root(&manifest.uuid).join("icon.png");
+            icon_img
+                .ok_or(LauncherProfileError::IconNotFound)?
+                .save(&icon_path)?;
+            Some(icon_path)
+        } else {
+            None
+        };
+        create_profile(&self.root, installer_profile, icon_path.as_deref())?;
+        Ok(())
+    }
+
+    fn artifacts(&self, uuid: &str) -> Vec<PathBuf> {
+        // The profile only consists of the game directory
+        vec![self.game_dir(uuid)]
+    }
+
+    fn remove_profile(&self, uuid: &str) -> io::Result<()> {
+        if let Err(e) = remove_profile(&self.root, uuid) {
+            error!("Failed to remove Modrinth App profile '{uuid}': {e}");
+        }
+        Ok(())
+    }
+
+    fn list_installed(&self) -> io::Result<Vec<Result<Manifest, String>>> {
+        list_manifests(self.root.join("profiles/"), "manifest.json")
+    }
+
+    fn java_path(&self, uuid: &str) -> Option<PathBuf> {
+        get_java_path(&self.root, uuid)
+    }
+
+    fn set_java_path(&self, uuid: &str, java: Option<&Path>) {
+        let java = java.map(|x| x.to_string_lossy().to_string());
+        if let Err(e) = set_java_path(&self.root, uuid, java.as_deref()) {
+            error!("Failed to update Modrinth App profile: {e}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::executor::block_on;
+
+    use super::*;
+    use crate::launcher::tests::{test_profile, TempDir, UUID};
+
+    /// Creates an `app.db` with the columns of the Modrinth App's `profiles` table the installer uses
+    fn backend(dir: &TempDir) -> ModrinthBackend {
+        std::fs::create_dir_all(dir.path().join("profiles")).unwrap();
+        open_database(dir.path())
+            .unwrap()
+            .execute_batch(
+                "CREATE TABLE profiles (path TEXT PRIMARY KEY, install_stage TEXT, name TEXT,
+                    icon_path TEXT, game_version TEXT, mod_loader TEXT, mod_loader_version TEXT,
+                    groups JSON, created INTEGER, modified INTEGER, override_java_path TEXT,
+                    override_extra_launch_args JSON, override_custom_env_vars JSON,
+                    override_mc_memory_max INTEGER)",
+            )
+            .unwrap();
+        ModrinthBackend {
+            root: dir.path().to_path_buf(),
+        }
+    }
+
+    fn profile_count(backend: &ModrinthBackend) -> i64 {
+        open_database(&backend.root)
+            .unwrap()
+            .query_row("SELECT COUNT(*) FROM profiles", [], |row| row.get(0))
+            .unwrap()
+    }
+
+    #[test]
+    fn write_profile_creates_row() {
+        let dir = TempDir::new();
+        let backend = backend(&dir);
+        block_on(backend.write_profile(&test_profile(), None)).unwrap();
+        let (name, loader, memory): (String, String, i32) = open_database(&backend.root)
+            .unwrap()
+            .query_row(
+                "SELECT name, mod_loader_version, override_mc_memory_max FROM profiles WHERE path = ?1",
+                params![UUID],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .unwrap();
+        assert_eq!(name, "Test Pack (Release)");
+        assert_eq!(loader, "0.15.11");
+        assert_eq!(memory, 4096);
+        // Updating doesn't add a second profile
+        block_on(backend.write_profile(&test_profile(), None)).unwrap();
+        assert_eq!(profile_count(&backend), 1);
+    }
+
+    fn overrides(backend: &ModrinthBackend) -> (String, Option<i32>) {
+        open_database(&backend.root)
+            .unwrap()
+            .query_row(
+                "SELECT override_extra_launch_args, override_mc_memory_max FROM profiles WHERE path = ?1",
+                params![UUID],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap()
+    }
+
+    #[test]
+    fn write_profile_only_replaces_set_overrides() {
+        let dir = TempDir::new();
+        let backend = backend(&dir);
+        let mut installer_profile = test_profile();
+        installer_profile.manifest.max_mem = None;
+        installer_profile.manifest.java_args = None;
+        block_on(backend.write_profile(&installer_profile, None)).unwrap();
+        assert_eq!(overrides(&backend), (String::from("[]"), None));
+        open_database(&backend.root)
+            .unwrap()
+            .execute(
+                "UPDATE profiles SET override_extra_launch_args = '[\"-Dfoo\"]',
+                    override_mc_memory_max = 6144 WHERE path = ?1",
+                params![UUID],
+            )
+            .unwrap();
+        // Overrides the pack doesn't set are the user's
+        block_on(backend.write_profile(&installer_profile, None)).unwrap();
+        assert_eq!(
+            overrides(&backend),
+            (String::from("[\"-Dfoo\"]"), Some(6144))
+        );
+        // The ones it sets replace the user's
+        block_on(backend.write_profile(&test_profile(), None)).unwrap();
+        assert_eq!(
+            overrides(&backend),
+            (String::from("[\"-XX:+UseG1GC\"]"), Some(4096))
+        );
+    }
+
+    #[test]
+    fn remove_profile_removes_row() {
+        let dir = TempDir::new();
+        let backend = backend(&dir);
+        block_on(backend.write_profile(&test_profile(), None)).unwrap();
+        backend.set_java_path(UUID, Some(Path::new("/usr/bin/java")));
+        assert_eq!(
+            backend.java_path(UUID),
+            Some(PathBuf::from("/usr/bin/java"))
+        );
+        backend.remove_profile(UUID).unwrap();
+        assert_eq!(profile_count(&backend), 0);
+        assert_eq!(backend.java_path(UUID), None);
+    }
+}
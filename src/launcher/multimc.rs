@@ -0,0 +1,612 @@
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+};
+
+use async_trait::async_trait;
+use image::DynamicImage;
+use isahc::AsyncReadResponseExt;
+use log::{error, warn};
+use serde_json::{json, Map, Value as JsonValue};
+
+use super::{find_in_path, flatpak_exports, list_manifests, LauncherBackend, LauncherLocation};
+use crate::{
+    get_app_data, instance_cfg, CachedHttpClient, InstallerProfile, LauncherProfileError, Loader,
+    MMCComponent, MMCPack, Manifest,
+};
+
+pub(crate) fn get_multimc_folder(multimc: &str) -> Result<PathBuf, String> {
+    let path = match env::consts::OS {
+        "linux" => get_app_data().join(format!(".local/share/{}", multimc)),
+        "windows" | "macos" => get_app_data().join(multimc),
+        _ => panic!("Unsupported os '{}'!", env::consts::OS),
+    };
+    match path.metadata() {
+        Ok(metadata) => {
+            if metadata.is_dir() && path.join("instances").is_dir() {
+                Ok(path)
+            } else {
+                Err(String::from("MultiMC directory is not a valid directory!"))
+            }
+        }
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// Locations of MultiMC based launchers besides the native ones `get_multimc_folder` checks
+fn multimc_folder_candidates() -> Vec<(&'static str, PathBuf)> {
+    let home = dirs::home_dir().unwrap();
+    match env::consts::OS {
+        "linux" => vec![
+            (
+                "Prism Launcher (Flatpak)",
+                home.join(".var/app/org.prismlauncher.PrismLauncher/data/PrismLauncher"),
+            ),
+            (
+                "MultiMC (Flatpak)",
+                home.join(".var/app/org.multimc.MultiMC/data/multimc"),
+            ),
+            ("MultiMC", home.join(".local/share/multimc")),
+            ("MultiMC (Portable)", home.join("MultiMC")),
+        ],
+        "windows" => {
+            let local_app_data = dirs::data_local_dir().unwrap();
+            vec![
+                (
+                    "Prism Launcher (Portable)",
+                    local_app_data.join("Programs/PrismLauncher"),
+                ),
+                (
+                    "Prism Launcher (Scoop)",
+                    home.join("scoop/persist/prismlauncher"),
+                ),
+                ("MultiMC (Portable)", home.join("MultiMC")),
+                ("MultiMC (Portable)", PathBuf::from("C:\\MultiMC")),
+                ("MultiMC (Scoop)", home.join("scoop/persist/multimc")),
+            ]
+        }
+        "macos" => vec![
+            ("MultiMC", PathBuf::from("/Applications/MultiMC.app/Data")),
+            ("MultiMC", home.join("Applications/MultiMC.app/Data")),
+        ],
+        _ => panic!("Unsupported os '{}'!", env::consts::OS),
+    }
+}
+
+const MULTIMC_META: &str = "https://meta.multimc.org/v1/";
+const PRISM_META: &str = "https://meta.prismlauncher.org/v1/";
+
+/// Components the installer generates, any other component in `mmc-pack.json` was added by the user
+const MANAGED_COMPONENTS: [&str; 7] = [
+    "org.lwjgl",
+    "org.lwjgl3",
+    "net.minecraft",
+    "net.fabricmc.intermediary",
+    "org.quiltmc.hashed",
+    "net.fabricmc.fabric-loader",
+    "org.quiltmc.quilt-loader",
+];
+
+fn component(uid: &str, version: &str) -> MMCComponent {
+    MMCComponent {
+        uid: String::from(uid),
+        version: String::from(version),
+        cachedVolatile: None,
+        dependencyOnly: None,
+        important: None,
+        extra: Map::new(),
+    }
+}
+
+/// The `requires` list of a component version from the launcher's meta server
+async fn get_component_requires(
+    meta: &str,
+    uid: &str,
+    version: &str,
+    http_client: &CachedHttpClient,
+) -> Option<Vec<(String, String)>> {
+    let text = http_client
+        .get_async(format!("{meta}{uid}/{version}.json"))
+        .await
+        .ok()?
+        .text()
+        .await
+        .ok()?;
+    let json: JsonValue = match serde_json::from_str(&text) {
+        Ok(v) => v,
+        Err(e) => {
+            warn!("Failed to get '{uid}' '{version}' from {meta}: {e}");
+            return None;
+        }
+    };
+    Some(
+        json.get("requires")?
+            .as_array()?
+            .iter()
+            .filter_map(|x| {
+                let uid = x.get("uid")?.as_str()?.to_string();
+                let version = x
+                    .get("equals")
+                    .or(x.get("suggests"))
+                    .and_then(|x| x.as_str())
+                    .unwrap_or_default()
+                    .to_string();
+                Some((uid, version))
+            })
+            .collect(),
+    )
+}
+
+/// MultiMC and launchers based on it (Prism Launcher, ...), packs are installed as `<root>/instances/<uuid>`
+pub(crate) struct MultiMCBackend {
+    pub root: PathBuf,
+}
+
+impl MultiMCBackend {
+    fn instance_cfg_path(&self, uuid: &str) -> PathBuf {
+        self.root.join(format!("instances/{uuid}/instance.cfg"))
+    }
+
+    /// Prism Launcher keeps its settings in `prismlauncher.cfg` and MultiMC in `multimc.cfg`, Prism
+    /// doesn't remove the `multimc.cfg` of a MultiMC folder it took over
+    fn is_prism(&self) -> bool {
+        self.root.join("prismlauncher.cfg").is_file()
+    }
+
+    fn meta_url(&self) -> &'static str {
+        if self.is_prism() {
+            PRISM_META
+        } else {
+            MULTIMC_META
+        }
+    }
+
+    fn instgroups_path(&self) -> PathBuf {
+        self.root.join("instances/instgroups.json")
+    }
+
+    fn read_instgroups(&self) -> Map<String, JsonValue> {
+        fs::read_to_string(self.instgroups_path())
+            .ok()
+            .and_then(|x| serde_json::from_str(&x).ok())
+            .unwrap_or_default()
+    }
+
+    /// Adds the instance to `group` in `instgroups.json`, instances the user already sorted into a group stay there
+    fn add_to_group(&self, uuid: &str, group: &str) -> Result<(), LauncherProfileError> {
+        let mut instgroups = self.read_instgroups();
+        let groups = instgroups.entry("groups").or_insert_with(|| json!({}));
+        if !groups.is_object() {
+            *groups = json!({});
+        }
+        let groups = groups.as_object_mut().unwrap();
+        if groups.values().any(|x| {
+            x["instances"]
+                .as_array()
+                .is_some_and(|x| x.iter().any(|x| x == uuid))
+        }) {
+            return Ok(());
+        }
+        let entry = groups
+            .entry(group)
+            .or_insert_with(|| json!({ "hidden": false, "instances": [] }));
+        if !entry.is_object() {
+            *entry = json!({ "hidden": false, "instances": [] });
+        }
+        match entry.get_mut("instances").and_then(|x| x.as_array_mut()) {
+            Some(instances) => instances.push(json!(uuid)),
+            None => entry["instances"] = json!([uuid]),
+        }
+        instgroups.insert(String::from("formatVersion"), json!("1"));
+        fs::write(
+            self.instgroups_path(),
+            serde_json::to_string_pretty(&instgroups)?,
+        )?;
+        Ok(())
+    }
+
+    /// Removes the instance from every group in `instgroups.json`, groups left empty are removed as well
+    fn remove_from_groups(&self, uuid: &str) -> Result<(), LauncherProfileError> {
+        let mut instgroups = self.read_instgroups();
+        let groups = match instgroups.get_mut("groups").and_then(|x| x.as_object_mut()) {
+            Some(v) => v,
+            None => return Ok(()),
+        };
+        groups.retain(
+            |_, group| match group.get_mut("instances").and_then(|x| x.as_array_mut()) {
+                Some(instances) => {
+                    let len = instances.len();
+                    instances.retain(|x| x != uuid);
+                    !(instances.is_empty() && len > 0)
+                }
+                None => true,
+            },
+        );
+        fs::write(
+            self.instgroups_path(),
+            serde_json::to_string_pretty(&instgroups)?,
+        )?;
+        Ok(())
+    }
+
+    /// Builds the full component list for the pack: LWJGL, Minecraft, the mappings the loader needs and the loader
+    ///
+    /// The dependencies are taken from the launcher's meta server, if it can't be reached LWJGL is left for the
+    /// launcher to resolve and intermediary is assumed (quilt only used hashed mappings before loader 0.19)
+    async fn resolve_components(
+        &self,
+        loader: &Loader,
+        http_client: &CachedHttpClient,
+    ) -> Vec<MMCComponent> {
+        let meta = self.meta_url();
+        let minecraft_version = &loader.minecraft_version;
+        let loader_uid = match &loader.r#type[..] {
+            "fabric" => "net.fabricmc.fabric-loader",
+            "quilt" => "org.quiltmc.quilt-loader",
+            _ => panic!("Invalid loader"),
+        };
+        let mut components = vec![];
+        let minecraft_requires =
+            get_component_requires(meta, "net.minecraft", minecraft_version, http_client)
+                .await
+                .unwrap_or_default();
+        for (uid, version) in minecraft_requires {
+            if uid.starts_with("org.lwjgl") && !version.is_empty() {
+                let mut lwjgl = component(&uid, &version);
+                lwjgl.cachedVolatile = Some(true);
+                lwjgl.dependencyOnly = Some(true);
+                components.push(lwjgl);
+            }
+        }
+        let mut minecraft = component("net.minecraft", minecraft_version);
+        minecraft.important = Some(true);
+        components.push(minecraft);
+        let mappings = get_component_requires(meta, loader_uid, &loader.version, http_client)
+            .await
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(uid, _)| uid)
+            .find(|uid| uid == "net.fabricmc.intermediary" || uid == "org.quiltmc.hashed")
+            .unwrap_or(String::from("net.fabricmc.intermediary"));
+        let mut mappings = component(&mappings, minecraft_version);
+        mappings.cachedVolatile = Some(true);
+        mappings.dependencyOnly = Some(true);
+        components.push(mappings);
+        components.push(component(loader_uid, &loader.version));
+        components
+    }
+
+    /// Writes the instance with the resolved `components`, settings the user changed are kept
+    fn write_instance(
+        &self,
+        installer_profile: &InstallerProfile,
+        mut components: Vec<MMCComponent>,
+        icon_img: Option<DynamicImage>,
+    ) -> Result<(), LauncherProfileError> {
+        let manifest = &installer_profile.manifest;
+        self.instance_root(&manifest.uuid);
+        let instance_cfg_path = self.instance_cfg_path(&manifest.uuid);
+        let pack_path = self
+            .root
+            .join(format!("instances/{}/mmc-pack.json", manifest.uuid));
+        // Keep components the user added (e.g. jar mods or agents)
+        let existing: Option<MMCPack> = fs::read_to_string(&pack_path)
+            .ok()
+            .and_then(|x| serde_json::from_str(&x).ok());
+        if let Some(existing) = existing {
+            components.extend(
+                existing
+                    .components
+                    .into_iter()
+                    .filter(|x| !MANAGED_COMPONENTS.contains(&x.uid.as_str())),
+            );
+        }
+        let pack = MMCPack {
+            components,
+            formatVersion: 1,
+        };
+        fs::write(pack_path, serde_json::to_string_pretty(&pack)?)?;
+        if instance_cfg::read_value(&instance_cfg_path, "InstanceType").is_none() {
+            instance_cfg::set_values(&instance_cfg_path, &[("InstanceType", Some("OneSix"))])?;
+        }
+        let override_mem = (manifest.max_mem.is_some() || manifest.min_mem.is_some())
+            .then(|| String::from("true"));
+        instance_cfg::set_managed_values(
+            &instance_cfg_path,
+            &self.root.join(format!(
+                "instances/{}/installer_managed.json",
+                manifest.uuid
+            )),
+            &[
+                ("iconKey", Some(manifest.uuid.clone())),
+                ("name", Some(installer_profile.instance_name())),
+                ("MaxMemAlloc", manifest.max_mem.map(|x| x.to_string())),
+                ("MinMemAlloc", manifest.min_mem.map(|x| x.to_string())),
+                ("OverrideMemory", override_mem),
+                ("JvmArgs", installer_profile.java_args().cloned()),
+                (
+                    "OverrideJavaArgs",
+                    installer_profile.java_args().map(|_| String::from("true")),
+                ),
+            ],
+        )?;
+        self.add_to_group(&manifest.uuid, &manifest.instance_group())?;
+        if manifest.icon {
+            icon_img
+                .ok_or(LauncherProfileError::IconNotFound)?
+                .save(self.root.join(format!("icons/{}.png", manifest.uuid)))?;
+        }
+        if let Some(java) = &installer_profile.java_path {
+            instance_cfg::set_values(
+                &instance_cfg_path,
+                &[
+                    ("OverrideJavaLocation", Some("true")),
+                    ("JavaPath", Some(&java.to_string_lossy())),
+                ],
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl LauncherBackend for MultiMCBackend {
+    fn detect() -> Vec<LauncherLocation> {
+        let mut launchers = vec![];
+        let mut roots = vec![];
+        for (name, id) in [("MultiMC", "MultiMC"), ("Prism Launcher", "PrismLauncher")] {
+            if let Ok(path) = get_multimc_folder(id) {
+                launchers.push(LauncherLocation {
+                    id: format!("multimc-{id}"),
+                    name: String::from(name),
+                });
+                roots.push(path.canonicalize().unwrap_or(path));
+            }
+        }
+        for (name, path) in multimc_folder_candidates() {
+            if !path.join("instances").is_dir() {
+                continue;
+            }
+            // Case insensitive filesystems can point an alternative path at a native install
+            let canonical = path.canonicalize().unwrap_or(path.clone());
+            if roots.contains(&canonical) {
+                continue;
+            }
+            roots.push(canonical);
+            launchers.push(LauncherLocation {
+                id: format!("custom-{}", path.display()),
+                name: String::from(name),
+            });
+        }
+        launchers
+    }
+
+    fn data_root(&self) -> PathBuf {
+        self.root.clone()
+    }
+
+    /// Kept files would turn the instance folder into an instance MultiMC can't load, so they are moved
+    /// out of it and restored when the pack is installed again
+    fn kept_dir(&self, uuid: &str) -> Option<PathBuf> {
+        Some(self.root.join(format!(".WC_OVHL/kept/{uuid}")))
+    }
+
+    fn game_dir(&self, uuid: &str) -> PathBuf {
+        self.root.join(format!("instances/{uuid}/.minecraft"))
+    }
+
+    async fn write_profile(
+        &self,
+        installer_profile: &InstallerProfile,
+        icon_img: Option<DynamicImage>,
+    ) -> Result<(), LauncherProfileError> {
+        let components = self
+            .resolve_components(
+                &installer_profile.manifest.loader,
+                &installer_profile.http_client,
+            )
+            .await;
+        self.write_instance(installer_profile, components, icon_img)
+    }
+
+    /// The whole instance (`instance.cfg`, `mmc-pack.json`, ...) and its icon
+    fn artifacts(&self, uuid: &str) -> Vec<PathBuf> {
+        vec![
+            self.root.join(format!("instances/{uuid}")),
+            self.root.join(format!("icons/{uuid}.png")),
+        ]
+    }
+
+    fn remove_profile(&self, uuid: &str) -> io::Result<()> {
+        if let Err(e) = self.remove_from_groups(uuid) {
+            error!("Failed to remove '{uuid}' from 'instgroups.json': {e}");
+        }
+        Ok(())
+    }
+
+    fn list_installed(&self) -> io::Result<Vec<Result<Manifest, String>>> {
+        list_manifests(self.root.join("instances/"), ".minecraft/manifest.json")
+    }
+
+    fn java_path(&self, uuid: &str) -> Option<PathBuf> {
+        let instance_cfg = self.instance_cfg_path(uuid);
+        let java = if instance_cfg::read_value(&instance_cfg, "OverrideJavaLocation").as_deref()
+            == Some("false")
+        {
+            None
+        } else {
+            instance_cfg::read_value(&instance_cfg, "JavaPath")
+        };
+        let java = java.or_else(|| {
+            ["prismlauncher.cfg", "multimc.cfg"]
+                .iter()
+                .find_map(|cfg| instance_cfg::read_value(&self.root.join(cfg), "JavaPath"))
+        })?;
+        let java = PathBuf::from(java);
+        // Launcher managed runtimes are stored relative to the data directory
+        if java.is_relative() && java.components().count() > 1 {
+            Some(self.root.join(java))
+        } else {
+            Some(java)
+        }
+    }
+
+    fn set_java_path(&self, uuid: &str, java: Option<&Path>) {
+        let instance_cfg = self.instance_cfg_path(uuid);
+        if !instance_cfg.exists() {
+            return;
+        }
+        let java = java.map(|x| x.to_string_lossy().to_string());
+        let result = instance_cfg::set_values(
+            &instance_cfg,
+            &[
+                (
+                    "OverrideJavaLocation",
+                    Some(if java.is_some() { "true" } else { "false" }),
+                ),
+                ("JavaPath", java.as_deref()),
+            ],
+        );
+        if let Err(e) = result {
+            error!("Failed to write 'instance.cfg': {e}");
+        }
+    }
+
+    fn executable_candidates(&self) -> Vec<PathBuf> {
+        let (name, app) = if self.is_prism() {
+            ("prismlauncher", "Prism Launcher")
+        } else {
+            ("MultiMC", "MultiMC")
+        };
+        // Portable installs keep the executable next to their data
+        let mut candidates = vec![self.root.join(name), self.root.join(format!("{name}.exe"))];
+        match env::consts::OS {
+            "linux" => {
+                candidates.extend(find_in_path(&name.to_lowercase()));
+                if self.is_prism() {
+                    candidates.extend(flatpak_exports("org.prismlauncher.PrismLauncher"));
+                }
+            }
+            "windows" => {
+                if let Some(local_app_data) = dirs::data_local_dir() {
+                    candidates
+                        .push(local_app_data.join(format!("Programs/PrismLauncher/{name}.exe")));
+                }
+            }
+            "macos" => {
+                candidates.push(PathBuf::from(format!(
+                    "/Applications/{app}.app/Contents/MacOS/{name}"
+                )));
+            }
+            _ => {}
+        }
+        candidates
+    }
+
+    fn prepare_launch(&self, uuid: &str) -> Option<Vec<String>> {
+        Some(vec![String::from("--launch"), uuid.to_string()])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::launcher::tests::{test_profile, TempDir, UUID};
+
+    fn backend(dir: &TempDir) -> MultiMCBackend {
+        fs::create_dir_all(dir.path().join("instances")).unwrap();
+        MultiMCBackend {
+            root: dir.path().to_path_buf(),
+        }
+    }
+
+    fn components() -> Vec<MMCComponent> {
+        vec![
+            component("net.minecraft", "1.20.1"),
+            component("net.fabricmc.fabric-loader", "0.15.11"),
+        ]
+    }
+
+    #[test]
+    fn write_profile_creates_instance() {
+        let dir = TempDir::new();
+        let backend = backend(&dir);
+        backend
+            .write_instance(&test_profile(), components(), None)
+            .unwrap();
+        let instance_cfg = backend.instance_cfg_path(UUID);
+        let value = |key| instance_cfg::read_value(&instance_cfg, key);
+        assert_eq!(value("InstanceType").as_deref(), Some("OneSix"));
+        assert_eq!(value("name").as_deref(), Some("Test Pack (Release)"));
+        assert_eq!(value("MaxMemAlloc").as_deref(), Some("4096"));
+        assert_eq!(value("MinMemAlloc").as_deref(), Some("2048"));
+        assert_eq!(value("JvmArgs").as_deref(), Some("-XX:+UseG1GC"));
+        let instgroups = backend.read_instgroups();
+        assert_eq!(
+            instgroups["groups"]["Test Pack"]["instances"],
+            json!([UUID])
+        );
+    }
+
+    #[test]
+    fn write_profile_keeps_user_components() {
+        let dir = TempDir::new();
+        let backend = backend(&dir);
+        backend
+            .write_instance(&test_profile(), components(), None)
+            .unwrap();
+        let pack_path = dir.path().join(format!("instances/{UUID}/mmc-pack.json"));
+        let mut pack: MMCPack =
+            serde_json::from_str(&fs::read_to_string(&pack_path).unwrap()).unwrap();
+        pack.components.push(component("custom.agent", "1"));
+        fs::write(&pack_path, serde_json::to_string(&pack).unwrap()).unwrap();
+        backend
+            .write_instance(&test_profile(), components(), None)
+            .unwrap();
+        let pack: MMCPack = serde_json::from_str(&fs::read_to_string(&pack_path).unwrap()).unwrap();
+        let uids: Vec<&str> = pack.components.iter().map(|x| x.uid.as_str()).collect();
+        assert_eq!(
+            uids,
+            [
+                "net.minecraft",
+                "net.fabricmc.fabric-loader",
+                "custom.agent"
+            ]
+        );
+    }
+
+    #[test]
+    fn remove_profile_removes_group() {
+        let dir = TempDir::new();
+        let backend = backend(&dir);
+        backend
+            .write_instance(&test_profile(), components(), None)
+            .unwrap();
+        backend.add_to_group("other", "Test Pack").unwrap();
+        backend.remove_profile(UUID).unwrap();
+        assert_eq!(
+            backend.read_instgroups()["groups"]["Test Pack"]["instances"],
+            json!(["other"])
+        );
+        backend.remove_profile("other").unwrap();
+        assert!(backend.read_instgroups()["groups"]
+            .get("Test Pack")
+            .is_none());
+    }
+
+    #[test]
+    fn is_prism_reads_launcher_config() {
+        let dir = TempDir::new();
+        let root = dir.path().join("prism");
+        fs::create_dir_all(root.join("instances")).unwrap();
+        let backend = MultiMCBackend { root: root.clone() };
+        fs::write(root.join("multimc.cfg"), "").unwrap();
+        assert_eq!(backend.meta_url(), MULTIMC_META);
+        fs::write(root.join("prismlauncher.cfg"), "").unwrap();
+        assert_eq!(backend.meta_url(), PRISM_META);
+        assert_eq!(
+            backend.executable_candidates()[0],
+            root.join("prismlauncher")
+        );
+    }
+}
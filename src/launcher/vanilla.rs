@@ -0,0 +1,554 @@
+use std::{
+    cell::RefCell,
+    env, fs, io,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use image::DynamicImage;
+use log::{error, info, warn};
+use serde_json::{Map, Value as JsonValue};
+
+use super::{find_in_path, flatpak_exports, list_manifests, LauncherBackend, LauncherLocation};
+use crate::{
+    get_app_data, image_to_base64, InstallerProfile, LauncherProfile, LauncherProfileError,
+    Manifest,
+};
+
+/// Every known location of the vanilla launcher's game directory, the native one first
+fn minecraft_folder_candidates() -> Vec<(&'static str, PathBuf)> {
+    match env::consts::OS {
+        "linux" => {
+            let home = dirs::home_dir().unwrap();
+            vec![
+                ("Vanilla", home.join(".minecraft")),
+                (
+                    "Vanilla (Flatpak)",
+                    home.join(".var/app/com.mojang.Minecraft/.minecraft"),
+                ),
+                (
+                    "Vanilla (Snap)",
+                    home.join("snap/mc-installer/current/.minecraft"),
+                ),
+            ]
+        }
+        "macos" => vec![("Vanilla", get_app_data().join("minecraft"))],
+        "windows" => vec![("Vanilla", get_app_data().join(".minecraft"))],
+        _ => panic!("Unsupported os '{}'!", env::consts::OS),
+    }
+}
+
+/// The game directory selected by the `vanilla` launcher id, the first candidate that exists
+pub(crate) fn get_minecraft_folder() -> PathBuf {
+    let candidates = minecraft_folder_candidates();
+    candidates
+        .iter()
+        .map(|(_, path)| path)
+        .find(|x| x.is_dir())
+        .unwrap_or(&candidates[0].1)
+        .clone()
+}
+
+/// Folder packs are installed to for the game directory `root`
+///
+/// The `vanilla` launcher keeps its packs in the app data directory like it always did, other
+/// locations are likely sandboxed and keep them next to the game directory
+pub(crate) fn get_vanilla_data_dir(root: &Path) -> PathBuf {
+    if root == get_minecraft_folder() {
+        get_app_data()
+    } else {
+        root.to_path_buf()
+    }
+}
+
+/// Profile fields the installer updates unless the user changed them in the launcher
+const MANAGED_PROFILE_KEYS: [&str; 3] = ["name", "icon", "javaArgs"];
+
+/// How often an update is retried when the launcher changes the file while we are writing it
+const PROFILES_WRITE_ATTEMPTS: usize = 3;
+/// Number of previous `launcher_profiles.json` versions kept in `.WC_OVHL/backups`
+const PROFILES_BACKUPS: usize = 5;
+/// The `launcher_profiles.json` from before the installer first changed it, never rotated out
+const ORIGINAL_PROFILES_BACKUP: &str = "launcher_profiles.json.original";
+
+fn read_launcher_profiles(root: &Path) -> Option<JsonValue> {
+    serde_json::from_str(&fs::read_to_string(root.join("launcher_profiles.json")).ok()?).ok()
+}
+
+/// Versions used by the profiles in `<root>/launcher_profiles.json`
+pub(crate) fn get_profile_versions(root: &Path) -> Vec<String> {
+    read_launcher_profiles(root)
+        .and_then(|x| x.get("profiles").and_then(|x| x.as_object()).cloned())
+        .unwrap_or_default()
+        .values()
+        .filter_map(|x| x.get("lastVersionId")?.as_str().map(String::from))
+        .collect()
+}
+
+/// Moves `launcher_profiles.json.<n>` to `<n + 1>` and stores `contents` as the newest backup
+fn backup_launcher_profiles(backup_dir: &Path, contents: &str) -> io::Result<()> {
+    fs::create_dir_all(backup_dir)?;
+    let backup = |n: usize| backup_dir.join(format!("launcher_profiles.json.{n}"));
+    for n in (1..PROFILES_BACKUPS).rev() {
+        if backup(n).exists() {
+            fs::rename(backup(n), backup(n + 1))?;
+        }
+    }
+    fs::write(backup(1), contents)
+}
+
+/// Applies `update` to the `profiles` object of `<root>/launcher_profiles.json`
+///
+/// The file is replaced atomically through a temporary file, if it changed while the update was
+/// being applied (e.g. the launcher is running) the update is redone on the new contents so the
+/// other writer's changes aren't lost. The version from before the installer's first change is kept
+/// in `backup_dir`, and with `backup` the old version is added to the rotating backups. Updates
+/// that don't change anything aren't written
+fn update_launcher_profiles<F>(
+    root: &Path,
+    backup_dir: &Path,
+    backup: bool,
+    update: F,
+) -> Result<(), LauncherProfileError>
+where
+    F: Fn(&mut Map<String, JsonValue>) -> Result<(), LauncherProfileError>,
+{
+    let lp_file_path = root.join("launcher_profiles.json");
+    let tmp_file_path = lp_file_path.with_extension("json.tmp");
+    for _ in 0..PROFILES_WRITE_ATTEMPTS {
+        let contents = fs::read_to_string(&lp_file_path)?;
+        let mut lp_obj: JsonValue = serde_json::from_str(&contents)?;
+        let original = lp_obj.clone();
+        let profiles = match lp_obj {
+            JsonValue::Object(ref mut obj) => {
+                match obj
+                    .get_mut("profiles")
+                    .ok_or(LauncherProfileError::NoProfiles)?
+                {
+                    JsonValue::Object(profiles) => profiles,
+                    _ => return Err(LauncherProfileError::ProfilesNotObject),
+                }
+            }
+            _ => return Err(LauncherProfileError::RootNotObject),
+        };
+        update(profiles)?;
+        if lp_obj == original {
+            return Ok(());
+        }
+        fs::write(&tmp_file_path, serde_json::to_string_pretty(&lp_obj)?)?;
+        if fs::read_to_string(&lp_file_path)? != contents {
+            warn!("'launcher_profiles.json' was modified while updating it, retrying");
+            fs::remove_file(&tmp_file_path)?;
+            continue;
+        }
+        let original_backup = backup_dir.join(ORIGINAL_PROFILES_BACKUP);
+        if !original_backup.exists() {
+            fs::create_dir_all(backup_dir)?;
+            fs::write(original_backup, &contents)?;
+        }
+        if backup {
+            backup_launcher_profiles(backup_dir, &contents)?;
+        }
+        fs::rename(&tmp_file_path, &lp_file_path)?;
+        return Ok(());
+    }
+    Err(LauncherProfileError::ConcurrentModification)
+}
+
+/// The official launcher, packs are installed to `<data_dir>/.WC_OVHL/<uuid>` and added to
+/// `<root>/launcher_profiles.json`
+pub(crate) struct VanillaBackend {
+    /// The `.minecraft` folder
+    pub root: PathBuf,
+    /// Folder the installer keeps packs and `launcher_profiles.json` backups in
+    pub data_dir: PathBuf,
+}
+
+impl VanillaBackend {
+    fn backup_dir(&self) -> PathBuf {
+        self.data_dir.join(".WC_OVHL/backups")
+    }
+
+    /// Only installs and uninstalls are backed up, launching a pack or changing its Java would
+    /// otherwise quickly push the user's own versions out of the backups
+    fn update_launcher_profiles<F>(
+        &self,
+        backup: bool,
+        update: F,
+    ) -> Result<(), LauncherProfileError>
+    where
+        F: Fn(&mut Map<String, JsonValue>) -> Result<(), LauncherProfileError>,
+    {
+        update_launcher_profiles(&self.root, &self.backup_dir(), backup, update)
+    }
+}
+
+#[async_trait]
+impl LauncherBackend for VanillaBackend {
+    fn detect() -> Vec<LauncherLocation> {
+        let default = get_minecraft_folder();
+        let mut launchers = vec![];
+        let mut roots = vec![];
+        for (name, path) in minecraft_folder_candidates() {
+            if !path.is_dir() {
+                continue;
+            }
+            let canonical = path.canonicalize().unwrap_or(path.clone());
+            if roots.contains(&canonical) {
+                continue;
+            }
+            roots.push(canonical);
+            launchers.push(LauncherLocation {
+                id: if path == default {
+                    String::from("vanilla")
+                } else {
+                    format!("vanilla-{}", path.display())
+                },
+                name: String::from(name),
+            });
+        }
+        launchers
+    }
+
+    fn data_root(&self) -> PathBuf {
+        self.data_dir.join(".WC_OVHL")
+    }
+
+    fn game_dir(&self, uuid: &str) -> PathBuf {
+        self.data_dir.join(format!(".WC_OVHL/{uuid}"))
+    }
+
+    fn minecraft_dir(&self) -> Option<PathBuf> {
+        Some(self.root.clone())
+    }
+
+    async fn write_profile(
+        &self,
+        installer_profile: &InstallerProfile,
+        icon_img: Option<DynamicImage>,
+    ) -> Result<(), LauncherProfileError> {
+        let now = SystemTime::now();
+        let now: DateTime<Utc> = now.into();
+        let now = now.to_rfc3339();
+        let manifest = &installer_profile.manifest;
+        let modpack_root = self.instance_root(&manifest.uuid);
+        let icon = match &icon_img {
+            Some(icon_img) if manifest.icon => image_to_base64(icon_img),
+            _ => String::from("Furnace"),
+        };
+        let mut jvm_args = String::new();
+        let java_args = installer_profile.java_args();
+        if java_args.is_none() && (manifest.max_mem.is_some() || manifest.min_mem.is_some()) {
+            jvm_args += "XX:+UnlockExperimentalVMOptions -XX:+UseG1GC -XX:G1NewSizePercent=20 -XX:G1ReservePercent=20 -XX:MaxGCPauseMillis=50 -XX:G1HeapRegionSize=32M";
+        }
+        if let Some(x) = java_args {
+            jvm_args += x
+        }
+        if let Some(x) = manifest.max_mem {
+            jvm_args += &format!(" -Xmx{}M", x)
+        }
+        if let Some(x) = manifest.min_mem {
+            jvm_args += &format!(" -Xms{}M", x)
+        }
+        let profile = LauncherProfile {
+            lastUsed: now.to_string(),
+            lastVersionId: manifest.loader.version_id(),
+            created: now,
+            name: installer_profile.instance_name(),
+            icon: Some(icon),
+            r#type: String::from("custom"),
+            gameDir: Some(modpack_root.to_str().unwrap().to_string()),
+            javaDir: installer_profile
+                .java_path
+                .as_ref()
+                .map(|x| x.to_string_lossy().to_string()),
+            javaArgs: if jvm_args.is_empty() {
+                None
+            } else {
+                Some(jvm_args)
+            },
+            logConfig: None,
+            logConfigIsXML: None,
+            resolution: None,
+        };
+        let profile = serde_json::to_value(profile)?;
+        // The values written by the last install, a field that differs from it was changed by the user
+        let record_path = modpack_root.join("installer_managed.json");
+        let record: Option<Map<String, JsonValue>> = fs::read_to_string(&record_path)
+            .ok()
+            .and_then(|x| serde_json::from_str(&x).ok());
+        let new_record = RefCell::new(Map::new());
+        self.update_launcher_profiles(true, |profiles| {
+            let mut written = Map::new();
+            match profiles.get_mut(&manifest.uuid) {
+                Some(JsonValue::Object(existing)) => {
+                    existing.insert(
+                        String::from("lastVersionId"),
+                        JsonValue::String(manifest.loader.version_id()),
+                    );
+                    if let Some(java) = &installer_profile.java_path {
+                        existing.insert(
+                            String::from("javaDir"),
+                            JsonValue::String(java.to_string_lossy().to_string()),
+                        );
+                    }
+                    for key in MANAGED_PROFILE_KEYS {
+                        if let Some(record) = &record {
+                            if existing.get(key) != record.get(key) {
+                                info!("Keeping user customized '{key}' of the launcher profile");
+                                if let Some(v) = record.get(key) {
+                                    written.insert(key.to_string(), v.clone());
+                                }
+                                continue;
+                            }
+                        }
+                        match profile.get(key).filter(|x| !x.is_null()) {
+                            Some(v) => {
+                                existing.insert(key.to_string(), v.clone());
+                                written.insert(key.to_string(), v.clone());
+                            }
+                            None => {
+                                existing.remove(key);
+                            }
+                        }
+                    }
+                }
+                _ => {
+                    for key in MANAGED_PROFILE_KEYS {
+                        if let Some(v) = profile.get(key).filter(|x| !x.is_null()) {
+                            written.insert(key.to_string(), v.clone());
+                        }
+                    }
+                    profiles.insert(manifest.uuid.clone(), profile.clone());
+                }
+            }
+            *new_record.borrow_mut() = written;
+            Ok(())
+        })?;
+        fs::write(
+            record_path,
+            serde_json::to_string(&new_record.into_inner())?,
+        )?;
+        Ok(())
+    }
+
+    fn artifacts(&self, uuid: &str) -> Vec<PathBuf> {
+        vec![self.game_dir(uuid)]
+    }
+
+    fn remove_profile(&self, uuid: &str) -> io::Result<()> {
+        let result = self.update_launcher_profiles(true, |profiles| {
+            profiles.remove(uuid);
+            Ok(())
+        });
+        if let Err(e) = result {
+            error!("Failed to remove the profile from 'launcher_profiles.json': {e}");
+        }
+        Ok(())
+    }
+
+    fn list_installed(&self) -> io::Result<Vec<Result<Manifest, String>>> {
+        list_manifests(self.data_dir.join(".WC_OVHL/"), "manifest.json")
+    }
+
+    fn java_path(&self, uuid: &str) -> Option<PathBuf> {
+        read_launcher_profiles(&self.root)?
+            .get("profiles")?
+            .get(uuid)?
+            .get("javaDir")?
+            .as_str()
+            .map(PathBuf::from)
+    }
+
+    fn set_java_path(&self, uuid: &str, java: Option<&Path>) {
+        let result = self.update_launcher_profiles(false, |profiles| {
+            if let Some(JsonValue::Object(profile)) = profiles.get_mut(uuid) {
+                match java {
+                    Some(java) => profile.insert(
+                        String::from("javaDir"),
+                        JsonValue::String(java.to_string_lossy().to_string()),
+                    ),
+                    None => profile.remove("javaDir"),
+                };
+            }
+            Ok(())
+        });
+        if let Err(e) = result {
+            error!("Failed to update 'launcher_profiles.json': {e}");
+        }
+    }
+
+    fn executable_candidates(&self) -> Vec<PathBuf> {
+        match env::consts::OS {
+            "linux" => {
+                let mut candidates: Vec<PathBuf> =
+                    find_in_path("minecraft-launcher").into_iter().collect();
+                candidates.extend(flatpak_exports("com.mojang.Minecraft"));
+                candidates
+            }
+            "windows" => ["ProgramFiles(x86)", "ProgramFiles"]
+                .iter()
+                .filter_map(|x| env::var(x).ok())
+                .map(|x| PathBuf::from(x).join("Minecraft Launcher/MinecraftLauncher.exe"))
+                .collect(),
+            "macos" => vec![PathBuf::from(
+                "/Applications/Minecraft.app/Contents/MacOS/launcher",
+            )],
+            _ => vec![],
+        }
+    }
+
+    /// The launcher can't be told which profile to start, marking it as the last used one selects it
+    fn prepare_launch(&self, uuid: &str) -> Option<Vec<String>> {
+        let now: DateTime<Utc> = SystemTime::now().into();
+        let result = self.update_launcher_profiles(false, |profiles| {
+            if let Some(JsonValue::Object(profile)) = profiles.get_mut(uuid) {
+                profile.insert(
+                    String::from("lastUsed"),
+                    JsonValue::String(now.to_rfc3339()),
+                );
+            }
+            Ok(())
+        });
+        if let Err(e) = result {
+            error!("Failed to select the profile in 'launcher_profiles.json': {e}");
+        }
+        Some(vec![
+            String::from("--workDir"),
+            self.root.to_string_lossy().to_string(),
+        ])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::executor::block_on;
+    use serde_json::json;
+
+    use super::*;
+    use crate::launcher::tests::{test_profile, TempDir, UUID};
+
+    fn backend(dir: &TempDir) -> VanillaBackend {
+        let root = dir.path().join(".minecraft");
+        fs::create_dir_all(&root).unwrap();
+        fs::write(
+            root.join("launcher_profiles.json"),
+            json!({ "profiles": {}, "settings": {} }).to_string(),
+        )
+        .unwrap();
+        VanillaBackend {
+            root,
+            data_dir: dir.path().to_path_buf(),
+        }
+    }
+
+    fn profiles(backend: &VanillaBackend) -> JsonValue {
+        read_launcher_profiles(&backend.root).unwrap()["profiles"].clone()
+    }
+
+    #[test]
+    fn write_profile_adds_profile() {
+        let dir = TempDir::new();
+        let backend = backend(&dir);
+        block_on(backend.write_profile(&test_profile(), None)).unwrap();
+        let profile = &profiles(&backend)[UUID];
+        assert_eq!(profile["lastVersionId"], "fabric-loader-0.15.11-1.20.1");
+        assert_eq!(profile["name"], "Test Pack (Release)");
+        assert_eq!(
+            profile["gameDir"],
+            dir.path()
+                .join(".WC_OVHL")
+                .join(UUID)
+                .to_string_lossy()
+                .as_ref()
+        );
+        let java_args = profile["javaArgs"].as_str().unwrap();
+        assert!(java_args.contains("-XX:+UseG1GC"));
+        assert!(java_args.contains("-Xmx4096M"));
+        assert!(java_args.contains("-Xms2048M"));
+        // The previous file is backed up next to the packs
+        assert!(backend
+            .backup_dir()
+            .join("launcher_profiles.json.1")
+            .is_file());
+    }
+
+    #[test]
+    fn write_profile_keeps_user_changes() {
+        let dir = TempDir::new();
+        let backend = backend(&dir);
+        block_on(backend.write_profile(&test_profile(), None)).unwrap();
+        backend
+            .update_launcher_profiles(false, |profiles| {
+                profiles[UUID]["name"] = json!("My Pack");
+                Ok(())
+            })
+            .unwrap();
+        let mut installer_profile = test_profile();
+        installer_profile.manifest.max_mem = Some(8192);
+        installer_profile.manifest.loader.version = String::from("0.16.0");
+        block_on(backend.write_profile(&installer_profile, None)).unwrap();
+        let profile = &profiles(&backend)[UUID];
+        assert_eq!(profile["name"], "My Pack");
+        assert_eq!(profile["lastVersionId"], "fabric-loader-0.16.0-1.20.1");
+        assert!(profile["javaArgs"].as_str().unwrap().contains("-Xmx8192M"));
+        // Once the user's value is changed back the installer manages it again
+        backend
+            .update_launcher_profiles(false, |profiles| {
+                profiles[UUID]["name"] = json!("Test Pack (Release)");
+                Ok(())
+            })
+            .unwrap();
+        installer_profile.instance_name = Some(String::from("Renamed"));
+        block_on(backend.write_profile(&installer_profile, None)).unwrap();
+        assert_eq!(profiles(&backend)[UUID]["name"], "Renamed");
+    }
+
+    #[test]
+    fn only_installs_rotate_backups() {
+        let dir = TempDir::new();
+        let backend = backend(&dir);
+        let read = || fs::read_to_string(backend.root.join("launcher_profiles.json")).unwrap();
+        let backup = |n: &str| {
+            backend
+                .backup_dir()
+                .join(format!("launcher_profiles.json.{n}"))
+        };
+        let before = read();
+        let mut installed = vec![];
+        for n in 0..=PROFILES_BACKUPS {
+            let mut installer_profile = test_profile();
+            installer_profile.manifest.max_mem = Some(4096 + n as i32);
+            installed.push(read());
+            block_on(backend.write_profile(&installer_profile, None)).unwrap();
+            backend.prepare_launch(UUID);
+            backend.set_java_path(UUID, Some(Path::new("/usr/bin/java")));
+        }
+        // Only the versions from before each install were backed up, pushing out the first one
+        for n in 1..=PROFILES_BACKUPS {
+            let contents = fs::read_to_string(backup(&n.to_string())).unwrap();
+            assert_eq!(contents, installed[installed.len() - n]);
+        }
+        assert!(!backup(&(PROFILES_BACKUPS + 1).to_string()).exists());
+        assert_eq!(fs::read_to_string(backup("original")).unwrap(), before);
+    }
+
+    #[test]
+    fn remove_profile_keeps_other_profiles() {
+        let dir = TempDir::new();
+        let backend = backend(&dir);
+        backend
+            .update_launcher_profiles(false, |profiles| {
+                profiles.insert(String::from("other"), json!({ "name": "Other" }));
+                Ok(())
+            })
+            .unwrap();
+        block_on(backend.write_profile(&test_profile(), None)).unwrap();
+        backend.remove_profile(UUID).unwrap();
+        let profiles = profiles(&backend);
+        assert!(profiles.get(UUID).is_none());
+        assert_eq!(profiles["other"]["name"], "Other");
+    }
+}
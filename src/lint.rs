@@ -0,0 +1,533 @@
+// Semantic checks of manifests for pack authors, run with `installer --lint <branch> [manifest.json]`
+use std::{collections::HashMap, fmt::Display, fs, path::Path};
+
+use futures::{future::LocalBoxFuture, FutureExt, StreamExt};
+use isahc::{http::StatusCode, AsyncReadResponseExt, Request};
+use serde::de::DeserializeOwned;
+use serde_json::Value as JsonValue;
+
+use crate::{
+    features, migrations, schema, CachedHttpClient, Condition, Downloadable, GithubRelease, Loader,
+    Manifest, ModrinthObject, CONCURRENCY, GH_API, GH_RAW, REPO,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Severity {
+    Error,
+    Warning,
+}
+
+/// A problem found in the manifest, `path` is the JSON pointer of the offending value
+#[derive(Debug, Clone)]
+pub(crate) struct LintIssue {
+    pub severity: Severity,
+    pub path: String,
+    pub message: String,
+}
+
+impl Display for LintIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        write!(f, "{severity}: {}: {}", self.path, self.message)
+    }
+}
+
+fn error(path: impl Into<String>, message: impl Into<String>) -> LintIssue {
+    LintIssue {
+        severity: Severity::Error,
+        path: path.into(),
+        message: message.into(),
+    }
+}
+
+fn warning(path: impl Into<String>, message: impl Into<String>) -> LintIssue {
+    LintIssue {
+        severity: Severity::Warning,
+        path: path.into(),
+        message: message.into(),
+    }
+}
+
+async fn get_json<T: DeserializeOwned>(
+    http_client: &CachedHttpClient,
+    url: &str,
+) -> Result<T, String> {
+    let mut resp = http_client
+        .get_async(url)
+        .await
+        .map_err(|e| e.to_string())?;
+    if resp.status() != StatusCode::OK {
+        return Err(format!("status code {}", resp.status().as_u16()));
+    }
+    serde_json::from_str(&resp.text().await.map_err(|e| e.to_string())?).map_err(|e| e.to_string())
+}
+
+/// Loads the manifest of `branch`, or `file` if given, and returns every problem found in it
+///
+/// `Err` is returned if the manifest can't be parsed at all, the remaining checks need a valid manifest
+pub(crate) async fn lint(
+    branch: &str,
+    file: Option<&Path>,
+    http_client: &CachedHttpClient,
+) -> Result<Vec<LintIssue>, String> {
+    let contents = match file {
+        Some(file) => fs::read_to_string(file).map_err(|e| e.to_string())?,
+        None => http_client
+            .get_async(format!("{GH_RAW}{REPO}{branch}/manifest.json"))
+            .await
+            .map_err(|e| e.to_string())?
+            .text()
+            .await
+            .map_err(|e| e.to_string())?,
+    };
+    let manifest: JsonValue = serde_json::from_str(&contents).map_err(|e| e.to_string())?;
+    migrations::check_installer_version(&manifest)?;
+    schema::validate_manifest(&manifest)?;
+    let manifest = migrations::parse(manifest)?;
+    let mut issues = check_features(&manifest);
+    issues.extend(check_duplicates(&manifest));
+    issues.extend(check_release(&manifest, branch, http_client).await);
+    issues.extend(check_downloads(&manifest, http_client).await);
+    Ok(issues)
+}
+
+/// Prints the problems found in the manifest, returns the exit code of the process
+pub(crate) fn run(branch: &str, file: Option<&Path>) -> i32 {
+    let issues = match futures::executor::block_on(lint(branch, file, &CachedHttpClient::new())) {
+        Ok(v) => v,
+        Err(e) => {
+            println!("error: {e}");
+            return 1;
+        }
+    };
+    for issue in &issues {
+        println!("{issue}");
+    }
+    let errors = issues
+        .iter()
+        .filter(|x| x.severity == Severity::Error)
+        .count();
+    println!("{errors} errors, {} warnings", issues.len() - errors);
+    if errors > 0 {
+        1
+    } else {
+        0
+    }
+}
+
+/// The feature ids used by every item in the manifest with the path of the field they are used in
+fn item_ids(manifest: &Manifest) -> Vec<(String, &str)> {
+    let mut ids = vec![];
+    ids.extend(downloadable_ids("mods", &manifest.mods));
+    ids.extend(downloadable_ids("shaderpacks", &manifest.shaderpacks));
+    ids.extend(downloadable_ids("resourcepacks", &manifest.resourcepacks));
+    for (i, include) in manifest.include.iter().enumerate() {
+        ids.extend(feature_ids(
+            format!("/include/{i}"),
+            &include.id,
+            &include.condition,
+        ));
+    }
+    for (i, include) in manifest.remote_include.iter().flatten().enumerate() {
+        ids.extend(feature_ids(
+            format!("/remote_include/{i}"),
+            &include.id,
+            &include.condition,
+        ));
+    }
+    for (i, java_args) in manifest.java_args_overrides.iter().enumerate() {
+        ids.extend(
+            java_args
+                .condition
+                .ids()
+                .into_iter()
+                .map(|x| (format!("/java_args_overrides/{i}/condition"), x)),
+        );
+    }
+    ids
+}
+
+fn downloadable_ids<'a, T: Downloadable>(list: &str, items: &'a [T]) -> Vec<(String, &'a str)> {
+    items
+        .iter()
+        .enumerate()
+        .flat_map(|(i, item)| {
+            feature_ids(format!("/{list}/{i}"), item.get_id(), item.get_condition())
+        })
+        .collect()
+}
+
+fn feature_ids<'a>(
+    path: String,
+    id: &'a str,
+    condition: &'a Option<Condition>,
+) -> Vec<(String, &'a str)> {
+    let mut ids = vec![(format!("{path}/id"), id)];
+    if let Some(condition) = condition {
+        ids.extend(
+            condition
+                .ids()
+                .into_iter()
+                .map(|x| (format!("{path}/condition"), x)),
+        );
+    }
+    ids
+}
+
+fn check_features(manifest: &Manifest) -> Vec<LintIssue> {
+    let mut issues = vec![];
+    let declared: Vec<&str> = manifest.features.iter().map(|x| x.id.as_str()).collect();
+    let ids = item_ids(manifest);
+    for (i, feature) in manifest.features.iter().enumerate() {
+        if declared[..i].contains(&feature.id.as_str()) {
+            issues.push(error(
+                format!("/features/{i}/id"),
+                format!("Feature '{}' is declared more than once", feature.id),
+            ));
+        }
+        // An exclusive feature without any items is how a group offers a "none" option
+        if !feature.exclusive && !ids.iter().any(|(_, id)| *id == feature.id) {
+            issues.push(warning(
+                format!("/features/{i}"),
+                format!(
+                    "Feature '{}' isn't used by any mod, pack or include",
+                    feature.id
+                ),
+            ));
+        }
+        if feature.exclusive && feature.group.is_none() {
+            issues.push(warning(
+                format!("/features/{i}/exclusive"),
+                format!(
+                    "Feature '{}' is exclusive but isn't part of a group",
+                    feature.id
+                ),
+            ));
+        }
+        for (field, ids) in [
+            ("requires", &feature.requires),
+            ("conflicts", &feature.conflicts),
+        ] {
+            for (j, id) in ids.iter().enumerate() {
+                if !declared.contains(&id.as_str()) {
+                    issues.push(error(
+                        format!("/features/{i}/{field}/{j}"),
+                        format!("Feature '{id}' isn't declared in 'features'"),
+                    ));
+                }
+            }
+        }
+    }
+    let mut exclusive_groups: Vec<&str> = vec![];
+    for feature in manifest.features.iter().filter(|x| x.exclusive) {
+        if let Some(group) = feature.group.as_deref() {
+            if !exclusive_groups.contains(&group) {
+                exclusive_groups.push(group);
+            }
+        }
+    }
+    for group in exclusive_groups {
+        let has_default = manifest
+            .features
+            .iter()
+            .any(|x| x.exclusive && x.default && x.group.as_deref() == Some(group));
+        if !has_default {
+            issues.push(warning(
+                String::from("/features"),
+                format!(
+                    "None of the options of the exclusive group '{group}' is enabled by default"
+                ),
+            ));
+        }
+    }
+    let defaults: Vec<String> = manifest
+        .features
+        .iter()
+        .filter(|x| x.default)
+        .map(|x| x.id.clone())
+        .collect();
+    if let Err(e) = features::validate(&manifest.features, &defaults) {
+        issues.push(error(
+            String::from("/features"),
+            format!("Default features: {e}"),
+        ));
+    }
+    for (path, id) in ids {
+        if id != "default" && !declared.contains(&id) {
+            issues.push(error(
+                path,
+                format!("Feature '{id}' isn't declared in 'features'"),
+            ));
+        }
+    }
+    issues
+}
+
+fn check_duplicates(manifest: &Manifest) -> Vec<LintIssue> {
+    let mut issues = vec![];
+    issues.extend(duplicate_downloadables("mods", &manifest.mods));
+    issues.extend(duplicate_downloadables(
+        "shaderpacks",
+        &manifest.shaderpacks,
+    ));
+    issues.extend(duplicate_downloadables(
+        "resourcepacks",
+        &manifest.resourcepacks,
+    ));
+    let mut include_ids: HashMap<&str, usize> = HashMap::new();
+    for (i, include) in manifest.include.iter().enumerate() {
+        match include_ids.get(include.id.as_str()) {
+            Some(first) => issues.push(warning(
+                format!("/include/{i}/id"),
+                format!(
+                    "'/include/{first}' is also downloaded as '{}.zip', only one of them is installed",
+                    include.id
+                ),
+            )),
+            None => {
+                include_ids.insert(&include.id, i);
+            }
+        }
+    }
+    issues
+}
+
+/// Updates match installed files by name so every name has to be unique
+fn duplicate_downloadables<T: Downloadable>(list: &str, items: &[T]) -> Vec<LintIssue> {
+    let mut issues = vec![];
+    let mut names: HashMap<&str, usize> = HashMap::new();
+    let mut locations: HashMap<(&str, &str), usize> = HashMap::new();
+    for (i, item) in items.iter().enumerate() {
+        match names.get(item.get_name().as_str()) {
+            Some(first) => issues.push(error(
+                format!("/{list}/{i}/name"),
+                format!(
+                    "'{}' is also the name of '/{list}/{first}', names have to be unique",
+                    item.get_name()
+                ),
+            )),
+            None => {
+                names.insert(item.get_name(), i);
+            }
+        }
+        let location = (item.get_source().as_str(), item.get_location().as_str());
+        match locations.get(&location) {
+            Some(first) => issues.push(warning(
+                format!("/{list}/{i}/location"),
+                format!("'/{list}/{first}' is downloaded from the same location"),
+            )),
+            None => {
+                locations.insert(location, i);
+            }
+        }
+    }
+    issues
+}
+
+/// Includes are downloaded as `<id>.zip` from the release tagged with the branch name, the release body
+/// maps the asset names to their md5 hashes
+async fn check_release(
+    manifest: &Manifest,
+    branch: &str,
+    http_client: &CachedHttpClient,
+) -> Vec<LintIssue> {
+    if manifest.include.is_empty() {
+        return vec![];
+    }
+    let url = format!("{GH_API}{REPO}releases/tags/{branch}");
+    let release: GithubRelease = match get_json(http_client, &url).await {
+        Ok(v) => v,
+        Err(e) => {
+            return vec![error(
+                "/include",
+                format!("Failed to get the release tagged '{branch}': {e}"),
+            )]
+        }
+    };
+    let mut issues = vec![];
+    let hashes: HashMap<String, String> = match release.body.as_deref().map(serde_json::from_str) {
+        Some(Ok(v)) => v,
+        Some(Err(e)) => {
+            issues.push(error(
+                "/include",
+                format!("The body of release '{branch}' isn't a map of asset hashes: {e}"),
+            ));
+            HashMap::new()
+        }
+        None => {
+            issues.push(error(
+                "/include",
+                format!("Release '{branch}' has no body with the asset hashes"),
+            ));
+            HashMap::new()
+        }
+    };
+    for (i, include) in manifest.include.iter().enumerate() {
+        let zip = format!("{}.zip", include.id);
+        if !release.assets.iter().any(|x| x.name == zip) {
+            issues.push(error(
+                format!("/include/{i}"),
+                format!("Release '{branch}' has no asset named '{zip}'"),
+            ));
+        }
+        if !hashes.is_empty() && !hashes.contains_key(&zip) {
+            issues.push(error(
+                format!("/include/{i}"),
+                format!("Release '{branch}' has no hash for '{zip}'"),
+            ));
+        }
+    }
+    issues
+}
+
+async fn check_loader(loader: &Loader, http_client: &CachedHttpClient) -> Vec<LintIssue> {
+    match loader.get_profile_json(http_client).await {
+        Ok(_) => vec![],
+        Err(e) => vec![error(
+            "/loader",
+            format!(
+                "Failed to get {} {} for Minecraft {}: {e}",
+                loader.r#type, loader.version, loader.minecraft_version
+            ),
+        )],
+    }
+}
+
+async fn check_modrinth<T: Downloadable>(
+    item: &T,
+    path: String,
+    r#type: &str,
+    loader: &Loader,
+    http_client: &CachedHttpClient,
+) -> Vec<LintIssue> {
+    let project = item.get_location();
+    let version = item.get_version();
+    let url = format!("https://api.modrinth.com/v2/project/{project}/version");
+    let versions: Vec<ModrinthObject> = match get_json(http_client, &url).await {
+        Ok(v) => v,
+        Err(e) => {
+            return vec![error(
+                path + "/location",
+                format!("Failed to get Modrinth project '{project}': {e}"),
+            )]
+        }
+    };
+    let matching: Vec<&ModrinthObject> = versions
+        .iter()
+        .filter(|x| &x.version_number == version)
+        .collect();
+    let supported: Vec<&&ModrinthObject> = matching
+        .iter()
+        .filter(|x| x.supports_loader(&loader.r#type, r#type))
+        .collect();
+    if matching.is_empty() {
+        vec![error(
+            path + "/version",
+            format!("Modrinth project '{project}' has no version '{version}'"),
+        )]
+    } else if supported.is_empty() {
+        vec![error(
+            path + "/version",
+            format!(
+                "Version '{version}' of '{project}' doesn't support {}",
+                loader.r#type
+            ),
+        )]
+    } else if !supported
+        .iter()
+        .any(|x| x.game_versions.contains(&loader.minecraft_version))
+    {
+        vec![warning(
+            path + "/version",
+            format!(
+                "Version '{version}' of '{project}' isn't marked as compatible with Minecraft {}",
+                loader.minecraft_version
+            ),
+        )]
+    } else {
+        vec![]
+    }
+}
+
+async fn check_url(path: String, url: &str, http_client: &CachedHttpClient) -> Vec<LintIssue> {
+    let request = match Request::head(url).body(()) {
+        Ok(v) => v,
+        Err(e) => return vec![error(path, format!("Invalid url '{url}': {e}"))],
+    };
+    match http_client.http_client.send_async(request).await {
+        // Some hosts don't allow HEAD requests, they are still reachable
+        Ok(resp)
+            if resp.status().is_success() || resp.status() == StatusCode::METHOD_NOT_ALLOWED =>
+        {
+            vec![]
+        }
+        Ok(resp) => vec![error(
+            path,
+            format!("'{url}' returned status code {}", resp.status().as_u16()),
+        )],
+        Err(e) => vec![error(path, format!("'{url}' is unreachable: {e}"))],
+    }
+}
+
+fn downloadable_checks<'a, T: Downloadable>(
+    list: &str,
+    r#type: &'a str,
+    items: &'a [T],
+    loader: &'a Loader,
+    http_client: &'a CachedHttpClient,
+) -> Vec<LocalBoxFuture<'a, Vec<LintIssue>>> {
+    items
+        .iter()
+        .enumerate()
+        .map(|(i, item)| {
+            let path = format!("/{list}/{i}");
+            match item.get_source().as_str() {
+                "modrinth" => check_modrinth(item, path, r#type, loader, http_client).boxed_local(),
+                _ => check_url(path + "/location", item.get_location(), http_client).boxed_local(),
+            }
+        })
+        .collect()
+}
+
+async fn check_downloads(manifest: &Manifest, http_client: &CachedHttpClient) -> Vec<LintIssue> {
+    let loader = &manifest.loader;
+    let mut checks = vec![check_loader(loader, http_client).boxed_local()];
+    checks.extend(downloadable_checks(
+        "mods",
+        "mod",
+        &manifest.mods,
+        loader,
+        http_client,
+    ));
+    checks.extend(downloadable_checks(
+        "shaderpacks",
+        "shaderpack",
+        &manifest.shaderpacks,
+        loader,
+        http_client,
+    ));
+    checks.extend(downloadable_checks(
+        "resourcepacks",
+        "resourcepack",
+        &manifest.resourcepacks,
+        loader,
+        http_client,
+    ));
+    for (i, include) in manifest.remote_include.iter().flatten().enumerate() {
+        checks.push(
+            check_url(
+                format!("/remote_include/{i}/location"),
+                &include.location,
+                http_client,
+            )
+            .boxed_local(),
+        );
+    }
+    futures::stream::iter(checks)
+        .buffered(CONCURRENCY)
+        .concat()
+        .await
+}
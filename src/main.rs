@@ -6,7 +6,6 @@ use async_trait::async_trait;
 use base64::{engine, Engine};
 use cached::proc_macro::cached;
 use cached::SizedCache;
-use chrono::{DateTime, Utc};
 use dioxus::desktop::tao::window::Icon;
 use dioxus::prelude::LaunchBuilder;
 use dioxus::desktop::{Config as DioxusConfig, LogicalSize, WindowBuilder};
@@ -17,11 +16,16 @@ use isahc::config::RedirectPolicy;
 use isahc::http::{HeaderMap, HeaderValue, StatusCode};
 use isahc::prelude::Configurable;
 use isahc::{AsyncBody, AsyncReadResponseExt, HttpClient, ReadResponseExt, Request, Response};
+use launcher::{
+    get_minecraft_folder, get_multimc_folder, get_profile_versions, get_vanilla_data_dir,
+};
 use log::{error, info, warn};
 use platform_info::{PlatformInfo, PlatformInfoAPI, UNameAPI};
 use regex::Regex;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use serde_json::Value as JsonValue;
+use sha1::{Digest, Sha1};
 use simplelog::{
     ColorChoice, CombinedLogger, Config as LogConfig, LevelFilter, TermLogger, TerminalMode,
     WriteLogger,
@@ -36,12 +40,20 @@ use std::{
     env, fs,
     io::Cursor,
     path::{Path, PathBuf},
-    time::SystemTime,
 };
 
+mod features;
 mod gui;
-
-const CURRENT_MANIFEST_VERSION: i32 = 3;
+mod instance_cfg;
+mod java;
+mod launcher;
+mod lint;
+mod migrations;
+mod mod_metadata;
+mod platform;
+mod schema;
+
+const CURRENT_MANIFEST_VERSION: i32 = 4;
 const GH_API: &str = "https://api.github.com/repos/";
 const GH_RAW: &str = "https://raw.githubusercontent.com/";
 const CONCURRENCY: usize = 14;
@@ -201,6 +213,7 @@ trait Downloadable {
         http_client: &CachedHttpClient,
     ) -> Result<PathBuf, DownloadError>;
 
+    #[allow(clippy::too_many_arguments)]
     fn new(
         name: String,
         source: String,
@@ -208,6 +221,7 @@ trait Downloadable {
         version: String,
         path: Option<PathBuf>,
         id: String,
+        condition: Option<Condition>,
         authors: Vec<Author>,
     ) -> Self;
     fn get_name(&self) -> &String;
@@ -215,23 +229,41 @@ trait Downloadable {
     fn get_version(&self) -> &String;
     fn get_path(&self) -> &Option<PathBuf>;
     fn get_id(&self) -> &String;
+    fn get_condition(&self) -> &Option<Condition>;
     fn get_source(&self) -> &String;
     fn get_authors(&self) -> &Vec<Author>;
+
+    /// Whether the item's feature is enabled and its condition is met
+    fn is_enabled(&self, enabled_features: &[String]) -> bool {
+        features::is_enabled(self.get_id(), self.get_condition(), enabled_features)
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
 struct Config {
     launcher: String,
     first_launch: Option<bool>, // option for backwars compatibiliy
-}
-
-#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+    /// Java executable selected for each pack, keyed by pack uuid
+    #[serde(default)]
+    java: HashMap<String, PathBuf>,
+    /// Download a matching java runtime for MultiMC packs without a selected java
+    #[serde(default)]
+    managed_java: bool,
+    /// Launcher instance name chosen for each pack, keyed by pack uuid
+    #[serde(default)]
+    instance_names: HashMap<String, String>,
+    /// Launcher executable used to start packs, keyed by launcher, found automatically if missing
+    #[serde(default)]
+    launcher_executables: HashMap<String, PathBuf>,
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone, JsonSchema)]
 struct Author {
     name: String,
     link: String,
 }
 
-#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone, JsonSchema)]
 struct Included {
     md5: String,
     files: Vec<String>,
@@ -270,6 +302,7 @@ macro_rules! gen_downloadble_impl {
                 version: String,
                 path: Option<PathBuf>,
                 id: String,
+                condition: Option<Condition>,
                 authors: Vec<Author>,
             ) -> Self {
                 Self {
@@ -279,6 +312,7 @@ macro_rules! gen_downloadble_impl {
                     version,
                     path,
                     id,
+                    condition,
                     authors,
                 }
             }
@@ -298,6 +332,9 @@ macro_rules! gen_downloadble_impl {
             fn get_id(&self) -> &String {
                 &self.id
             }
+            fn get_condition(&self) -> &Option<Condition> {
+                &self.condition
+            }
             fn get_source(&self) -> &String {
                 &self.source
             }
@@ -308,130 +345,289 @@ macro_rules! gen_downloadble_impl {
     };
 }
 
-#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone, JsonSchema)]
 struct Mod {
     name: String,
+    /// Where the file is downloaded from
+    #[schemars(regex(pattern = r"^(modrinth|ddl|mediafire)$"))]
     source: String,
+    /// Modrinth slug, direct download link or Mediafire download page depending on `source`
     location: String,
+    /// Exact Modrinth version number, for other sources it has to change when the file does
     version: String,
+    /// Set by the installer in the local copy of the manifest
     path: Option<PathBuf>,
+    /// Feature required for the file to be installed, `default` is always enabled
     #[serde(default = "default_id")]
     id: String,
+    /// Extra condition on the enabled features and platform for the file to be installed
+    condition: Option<Condition>,
     authors: Vec<Author>,
 }
 
-#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone, JsonSchema)]
 struct Shaderpack {
     name: String,
+    /// Where the file is downloaded from
+    #[schemars(regex(pattern = r"^(modrinth|ddl|mediafire)$"))]
     source: String,
+    /// Modrinth slug, direct download link or Mediafire download page depending on `source`
     location: String,
+    /// Exact Modrinth version number, for other sources it has to change when the file does
     version: String,
+    /// Set by the installer in the local copy of the manifest
     path: Option<PathBuf>,
+    /// Feature required for the file to be installed, `default` is always enabled
     #[serde(default = "default_id")]
     id: String,
+    /// Extra condition on the enabled features and platform for the file to be installed
+    condition: Option<Condition>,
     authors: Vec<Author>,
 }
 
-#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone, JsonSchema)]
 struct Resourcepack {
     name: String,
+    /// Where the file is downloaded from
+    #[schemars(regex(pattern = r"^(modrinth|ddl|mediafire)$"))]
     source: String,
+    /// Modrinth slug, direct download link or Mediafire download page depending on `source`
     location: String,
+    /// Exact Modrinth version number, for other sources it has to change when the file does
     version: String,
+    /// Set by the installer in the local copy of the manifest
     path: Option<PathBuf>,
+    /// Feature required for the file to be installed, `default` is always enabled
     #[serde(default = "default_id")]
     id: String,
+    /// Extra condition on the enabled features and platform for the file to be installed
+    condition: Option<Condition>,
     authors: Vec<Author>,
 }
 
 gen_downloadble_impl!(Mod, "mod");
 gen_downloadble_impl!(Shaderpack, "shaderpack");
 gen_downloadble_impl!(Resourcepack, "resourcepack");
-#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, JsonSchema)]
 struct Loader {
+    #[schemars(regex(pattern = r"^(fabric|quilt)$"))]
     r#type: String,
     version: String,
     minecraft_version: String,
 }
 
 impl Loader {
-    async fn download(&self, root: &Path, _: &str, http_client: &CachedHttpClient) -> PathBuf {
+    /// Name of the version directory the loader is installed under in `.minecraft/versions`
+    fn version_id(&self) -> String {
         match self.r#type.as_str() {
-            "fabric" => {
-                download_loader_json(
-                    &format!(
-                        "https://meta.fabricmc.net/v2/versions/loader/{}/{}/profile/json",
-                        self.minecraft_version, self.version
-                    ),
-                    &format!("fabric-loader-{}-{}", self.version, self.minecraft_version),
-                    root,
-                    http_client,
-                )
-                .await
-            }
-            "quilt" => {
-                download_loader_json(
-                    &format!(
-                        "https://meta.quiltmc.org/v3/versions/loader/{}/{}/profile/json",
-                        self.minecraft_version, self.version
-                    ),
-                    &format!("quilt-loader-{}-{}", self.version, self.minecraft_version),
-                    root,
-                    http_client,
-                )
-                .await
-            }
+            "fabric" => format!("fabric-loader-{}-{}", self.version, self.minecraft_version),
+            "quilt" => format!("quilt-loader-{}-{}", self.version, self.minecraft_version),
             _ => panic!("Unsupported loader '{}'!", self.r#type.as_str()),
         }
     }
+
+    /// Url of the loader's launcher profile json
+    fn profile_url(&self) -> String {
+        match self.r#type.as_str() {
+            "fabric" => format!(
+                "https://meta.fabricmc.net/v2/versions/loader/{}/{}/profile/json",
+                self.minecraft_version, self.version
+            ),
+            "quilt" => format!(
+                "https://meta.quiltmc.org/v3/versions/loader/{}/{}/profile/json",
+                self.minecraft_version, self.version
+            ),
+            _ => panic!("Unsupported loader '{}'!", self.r#type.as_str()),
+        }
+    }
+
+    async fn get_profile_json(&self, http_client: &CachedHttpClient) -> Result<JsonValue, String> {
+        serde_json::from_str(
+            &http_client
+                .get_async(self.profile_url())
+                .await
+                .map_err(|e| e.to_string())?
+                .text()
+                .await
+                .map_err(|e| e.to_string())?,
+        )
+        .map_err(|e| e.to_string())
+    }
+
+    async fn download(
+        &self,
+        root: &Path,
+        _: &str,
+        http_client: &CachedHttpClient,
+    ) -> Result<PathBuf, DownloadError> {
+        let version_id = self.version_id();
+        let loader_path =
+            download_loader_json(&self.profile_url(), &version_id, root, http_client).await?;
+        let profile_json = match fs::read_to_string(loader_path.join(format!("{version_id}.json")))
+        {
+            Ok(v) => v,
+            Err(e) => return Err(DownloadError::IoError(version_id, e)),
+        };
+        let profile: LoaderProfile = match serde_json::from_str(&profile_json) {
+            Ok(v) => v,
+            Err(e) => return Err(DownloadError::FailedToParseResponse(version_id, e)),
+        };
+        download_loader_libraries(profile.libraries, root, http_client).await?;
+        Ok(loader_path)
+    }
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
+struct LoaderLibrary {
+    name: String,
+    url: Option<String>,
+    sha1: Option<String>,
+    size: Option<u64>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct LoaderProfile {
+    libraries: Vec<LoaderLibrary>,
+}
+
+impl LoaderLibrary {
+    /// Converts the maven coordinate (`group:artifact:version[:classifier][@extension]`) into a repository path
+    fn path(&self) -> Option<String> {
+        let (coordinate, extension) = match self.name.split_once('@') {
+            Some((coordinate, extension)) => (coordinate, extension),
+            None => (self.name.as_str(), "jar"),
+        };
+        let parts: Vec<&str> = coordinate.split(':').collect();
+        let (group, artifact, version) = (parts.first()?, parts.get(1)?, parts.get(2)?);
+        let filename = match parts.get(3) {
+            Some(classifier) => format!("{artifact}-{version}-{classifier}.{extension}"),
+            None => format!("{artifact}-{version}.{extension}"),
+        };
+        Some(format!(
+            "{}/{artifact}/{version}/{filename}",
+            group.replace('.', "/")
+        ))
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, JsonSchema)]
 struct Feature {
+    /// Id referenced by the `id` field of mods, packs and includes
     id: String,
     name: String,
+    /// Whether the feature is enabled on first install
     default: bool,
     #[serde(default = "default_hidden")]
     hidden: bool,
+    /// Tooltip shown when hovering the feature
     description: Option<String>,
-}
-#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+    /// Ids of features that are enabled together with this feature
+    #[serde(default)]
+    requires: Vec<String>,
+    /// Ids of features that can't be enabled together with this feature
+    #[serde(default)]
+    conflicts: Vec<String>,
+    /// Heading the feature is listed under, features of the same group are shown together
+    group: Option<String>,
+    /// Whether only one exclusive feature of the group can be enabled, they are shown as radio buttons
+    #[serde(default)]
+    exclusive: bool,
+}
+
+/// Condition on the enabled features and the platform, either a feature id, a list of conditions that
+/// all have to be met or an object with one of `all`, `any`, `not`, `os` or `arch`
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, JsonSchema)]
+#[serde(untagged, deny_unknown_fields)]
+enum Condition {
+    Feature(String),
+    List(Vec<Condition>),
+    All {
+        all: Vec<Condition>,
+    },
+    Any {
+        any: Vec<Condition>,
+    },
+    Not {
+        not: Box<Condition>,
+    },
+    Os {
+        #[schemars(regex(pattern = r"^(linux|macos|windows)$"))]
+        os: String,
+    },
+    Arch {
+        #[schemars(regex(pattern = r"^(x86_64|aarch64|x86|arm)$"))]
+        arch: String,
+    },
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, JsonSchema)]
+struct JavaArgsOverride {
+    condition: Condition,
+    /// Replaces `java_args` when the condition is met
+    java_args: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, JsonSchema)]
 struct Include {
+    /// Path of the file or folder in the modpack repository
     location: String,
     #[serde(default = "default_id")]
     id: String,
+    /// Extra condition on the enabled features and platform for the include to be installed
+    condition: Option<Condition>,
     name: Option<String>,
     authors: Option<Vec<Author>>,
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, JsonSchema)]
 struct RemoteInclude {
+    /// Direct download link of the zip file
     location: String,
+    /// Folder the zip is extracted to, relative to the modpack root
     path: Option<String>,
     #[serde(default = "default_id")]
     id: String,
+    /// Extra condition on the enabled features and platform for the include to be installed
+    condition: Option<Condition>,
     version: String,
     name: Option<String>,
     authors: Option<Vec<Author>>,
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, JsonSchema)]
 struct Manifest {
+    /// Manifest version the modpack was created for
     manifest_version: i32,
+    /// Oldest installer version that can install the modpack, older installers ask the user to update
+    #[schemars(regex(pattern = r"^v?\d+(\.\d+){0,2}([-+].+)?$"))]
+    min_installer_version: Option<String>,
+    /// Has to change for the installer to know an update is available
     modpack_version: String,
     name: String,
+    /// Name of the modpack version
     subtitle: String,
+    /// Id of the tab the version appears in, `0` is the default tab
     tab_group: Option<usize>,
     tab_title: Option<String>,
+    /// Background color of the version boxes in `#rrggbb` format
+    #[schemars(regex(pattern = r"^#[0-9a-fA-F]{6}$"))]
     tab_color: Option<String>,
+    /// Url of the tab's background image
     tab_background: Option<String>,
+    /// Url of the tab's primary font
     tab_primary_font: Option<String>,
+    /// Url of the tab's secondary font
     tab_secondary_font: Option<String>,
+    /// Url of the settings menu's background image
     settings_background: Option<String>,
     popup_title: Option<String>,
+    /// Contents of a popup shown before installing with an option to cancel
     popup_contents: Option<String>,
+    /// Html shown as the modpack's description
     description: String,
+    /// Whether the modpack root contains an `icon.png`
     icon: bool,
+    /// UUID4 shared by all versions of the modpack but different across branches
     uuid: String,
     loader: Loader,
     mods: Vec<Mod>,
@@ -440,15 +636,42 @@ struct Manifest {
     remote_include: Option<Vec<RemoteInclude>>,
     include: Vec<Include>,
     features: Vec<Feature>,
+    /// Set by the installer in the local copy of the manifest
     #[serde(default = "default_enabled_features")]
     enabled_features: Vec<String>,
+    /// Set by the installer in the local copy of the manifest
     included_files: Option<HashMap<String, Included>>,
+    /// Set by the installer in the local copy of the manifest
     source: Option<String>,
+    /// Set by the installer in the local copy of the manifest
     installer_path: Option<String>,
+    /// Xmx in MB
     max_mem: Option<i32>,
+    /// Xms in MB
     min_mem: Option<i32>,
+    /// Arguments passed to the jvm
     java_args: Option<String>,
+    /// The first override whose condition is met replaces `java_args`
+    #[serde(default)]
+    java_args_overrides: Vec<JavaArgsOverride>,
+    /// Name of the launcher instance, defaults to `<name> (<subtitle>)` so branches can be told apart
+    instance_name: Option<String>,
+    /// Group the instance is sorted into by launchers that support groups, defaults to `name`
+    instance_group: Option<String>,
+}
+
+impl Manifest {
+    fn instance_name(&self) -> String {
+        self.instance_name
+            .clone()
+            .unwrap_or_else(|| format!("{} ({})", self.name, self.subtitle))
+    }
+
+    fn instance_group(&self) -> String {
+        self.instance_group.clone().unwrap_or_else(|| self.name.clone())
+    }
 }
+
 #[allow(non_snake_case)]
 #[derive(Debug, Deserialize, Serialize)]
 struct LauncherProfile {
@@ -475,6 +698,17 @@ struct ModrinthObject {
     version_number: String,
     files: Vec<ModrinthFile>,
     loaders: Vec<String>,
+    #[serde(default)]
+    game_versions: Vec<String>,
+}
+
+impl ModrinthObject {
+    /// Whether the version can be installed with `loader_type`, shaderpacks don't depend on the loader
+    fn supports_loader(&self, loader_type: &str, r#type: &str) -> bool {
+        self.loaders.contains(&String::from("minecraft"))
+            || self.loaders.contains(&String::from(loader_type))
+            || r#type == "shaderpack"
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -514,6 +748,9 @@ struct MMCComponent {
     important: Option<bool>,
     uid: String,
     version: String,
+    /// Fields the launcher caches or the user set that the installer doesn't touch
+    #[serde(flatten)]
+    extra: serde_json::Map<String, JsonValue>,
 }
 
 #[allow(non_snake_case)]
@@ -532,6 +769,7 @@ enum DownloadError {
     MissingFilename(String),
     CouldNotFindItem(String),
     MedafireMissingDDL(String),
+    HashMismatch(String),
 }
 
 impl Display for DownloadError {
@@ -563,6 +801,9 @@ impl Display for DownloadError {
             DownloadError::MedafireMissingDDL(item) => {
                 write!(f, "Could not get DDL link from Nediafire: '{item}'")
             }
+            DownloadError::HashMismatch(item) => {
+                write!(f, "Hash mismatch when attempting to download: '{item}'")
+            }
         }
     }
 }
@@ -578,6 +819,9 @@ enum LauncherProfileError {
     RootNotObject,
     IconNotFound,
     InvalidIcon(image::error::ImageError),
+    InvalidMetadata(String),
+    InvalidDatabase(rusqlite::Error),
+    ConcurrentModification,
 }
 
 impl Display for LauncherProfileError {
@@ -606,6 +850,18 @@ impl Display for LauncherProfileError {
                 f,
                 "Encountered image error when creating launcher profile: {e}"
             ),
+            LauncherProfileError::InvalidMetadata(e) => write!(
+                f,
+                "Failed to get version metadata when creating launcher profile: {e}"
+            ),
+            LauncherProfileError::InvalidDatabase(e) => write!(
+                f,
+                "Encountered database error when creating launcher profile: {e}"
+            ),
+            LauncherProfileError::ConcurrentModification => write!(
+                f,
+                "'launcher_profiles.json' kept changing while it was being updated, please close the launcher and try again"
+            ),
         }
     }
 }
@@ -630,6 +886,42 @@ impl From<image::error::ImageError> for LauncherProfileError {
     }
 }
 
+impl From<rusqlite::Error> for LauncherProfileError {
+    fn from(value: rusqlite::Error) -> Self {
+        LauncherProfileError::InvalidDatabase(value)
+    }
+}
+
+#[derive(Debug)]
+enum LaunchError {
+    Unsupported(String),
+    ExecutableNotFound(String),
+    IoError(std::io::Error),
+}
+
+impl Display for LaunchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LaunchError::Unsupported(launcher) => write!(
+                f,
+                "{launcher} can't start modpacks directly, please start it from the launcher"
+            ),
+            LaunchError::ExecutableNotFound(launcher) => write!(
+                f,
+                "Couldn't find the {launcher} executable, please select it in the settings"
+            ),
+            LaunchError::IoError(e) => write!(f, "Failed to start the launcher: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for LaunchError {}
+
+impl From<std::io::Error> for LaunchError {
+    fn from(value: std::io::Error) -> Self {
+        LaunchError::IoError(value)
+    }
+}
 
 fn get_filename(headers: &HeaderMap<HeaderValue>, url: &str) -> Result<String, DownloadError> {
     let filename = if let Some(x) = headers.get("content-disposition") {
@@ -666,38 +958,221 @@ fn get_filename(headers: &HeaderMap<HeaderValue>, url: &str) -> Result<String, D
     Ok(filename)
 }
 
+fn get_loader_record_path(minecraft_dir: &Path) -> PathBuf {
+    get_vanilla_data_dir(minecraft_dir).join(".WC_OVHL/loaders.json")
+}
+
+/// Returns the loader versions in `.minecraft/versions` that were created by the installer
+///
+/// Loader versions that aren't recorded, including ones from installers that didn't keep a record,
+/// belong to the user since the official Fabric and Quilt installers create the same versions
+fn get_tracked_loaders(minecraft_dir: &Path) -> Vec<String> {
+    match fs::read_to_string(get_loader_record_path(minecraft_dir)) {
+        Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|e| {
+            warn!("Failed to parse loader record: {e}");
+            vec![]
+        }),
+        Err(_) => vec![],
+    }
+}
+
+fn set_tracked_loaders(minecraft_dir: &Path, loaders: &[String]) {
+    let path = get_loader_record_path(minecraft_dir);
+    if let Err(e) = fs::create_dir_all(path.parent().unwrap()).and_then(|_| {
+        fs::write(
+            &path,
+            serde_json::to_string(loaders).expect("Failed to serialize loader record!"),
+        )
+    }) {
+        error!("Failed to write loader record: {e}");
+    }
+}
+
+fn is_valid_loader_json(contents: &str, loader_name: &str) -> bool {
+    match serde_json::from_str::<JsonValue>(contents) {
+        Ok(json) => json.get("id").and_then(|id| id.as_str()) == Some(loader_name),
+        Err(_) => false,
+    }
+}
+
 async fn download_loader_json(
     url: &str,
     loader_name: &str,
     root: &Path,
     http_client: &CachedHttpClient,
-) -> PathBuf {
+) -> Result<PathBuf, DownloadError> {
     let loader_path = root.join(Path::new(&format!("versions/{}", &loader_name)));
-    if loader_path
-        .join(Path::new(&format!("{}.json", &loader_name)))
-        .exists()
+    let json_path = loader_path.join(Path::new(&format!("{}.json", &loader_name)));
+    let jar_path = loader_path.join(Path::new(&format!("{}.jar", &loader_name)));
+    let created = !loader_path.exists();
+    // The version json of a loader version never changes
+    if fs::read_to_string(&json_path).is_ok_and(|x| is_valid_loader_json(&x, loader_name))
+        && jar_path.exists()
     {
-        return PathBuf::new();
+        return Ok(loader_path);
+    }
+    let resp = match http_client.get_async(url).await {
+        Ok(mut resp) => resp
+            .text()
+            .await
+            .map_err(|e| DownloadError::IoError(loader_name.to_string(), e))?,
+        Err(e) => return Err(DownloadError::HttpError(loader_name.to_string(), e)),
+    };
+    if !is_valid_loader_json(&resp, loader_name) {
+        return Err(DownloadError::CouldNotFindItem(loader_name.to_string()));
+    }
+    info!("Writing loader '{loader_name}' to '{loader_path:#?}'");
+    let write = || -> std::io::Result<()> {
+        fs::create_dir_all(&loader_path)?;
+        fs::write(&json_path, &resp)?;
+        fs::write(&jar_path, "")
+    };
+    write().map_err(|e| DownloadError::IoError(loader_name.to_string(), e))?;
+    let mut tracked = get_tracked_loaders(root);
+    if created && !tracked.iter().any(|x| x == loader_name) {
+        tracked.push(loader_name.to_string());
+        set_tracked_loaders(root, &tracked);
+    }
+    Ok(loader_path)
+}
+
+fn sha1_hex(bytes: &[u8]) -> String {
+    format!("{:x}", Sha1::digest(bytes))
+}
+
+/// Downloads the libraries of a loader profile into `.minecraft/libraries` so the launcher doesn't have to on first launch
+async fn download_loader_libraries(
+    libraries: Vec<LoaderLibrary>,
+    root: &Path,
+    http_client: &CachedHttpClient,
+) -> Result<(), DownloadError> {
+    let results = futures::stream::iter(libraries.into_iter().map(|library| async move {
+        let path = match library.path() {
+            Some(v) => v,
+            None => {
+                warn!("Skipping library with invalid name: '{}'", library.name);
+                return Ok(());
+            }
+        };
+        let dist = root.join("libraries").join(&path);
+        let url = format!(
+            "{}{}",
+            library
+                .url
+                .as_deref()
+                .unwrap_or("https://libraries.minecraft.net/"),
+            path
+        );
+        // Fabric includes hashes in the profile, for other loaders we ask the maven repository
+        let sha1 = match library.sha1 {
+            Some(v) => Some(v),
+            None => match http_client.get_nocache(format!("{url}.sha1")).await {
+                Ok(mut resp) if resp.status() == StatusCode::OK => resp
+                    .text()
+                    .await
+                    .ok()
+                    .and_then(|x| x.split_whitespace().next().map(str::to_string)),
+                _ => None,
+            },
+        };
+        if let Ok(existing) = fs::read(&dist) {
+            match &sha1 {
+                Some(sha1) if &sha1_hex(&existing) == sha1 => return Ok(()),
+                None => return Ok(()),
+                _ => warn!("Library '{}' is corrupted, redownloading", library.name),
+            }
+        }
+        let mut resp = match http_client.get_nocache(&url).await {
+            Ok(v) => v,
+            Err(e) => return Err(DownloadError::HttpError(library.name, e)),
+        };
+        if resp.status() != StatusCode::OK {
+            return Err(DownloadError::Non200StatusCode(
+                library.name,
+                resp.status().as_u16(),
+            ));
+        }
+        let contents = match resp.bytes().await {
+            Ok(v) => v,
+            Err(e) => return Err(DownloadError::IoError(library.name, e)),
+        };
+        match sha1 {
+            Some(sha1) if sha1_hex(&contents) != sha1 => {
+                return Err(DownloadError::HashMismatch(library.name))
+            }
+            None => warn!("No hash available for library '{}'", library.name),
+            _ => (),
+        }
+        if let Some(parent) = dist.parent() {
+            if let Err(e) = fs::create_dir_all(parent) {
+                return Err(DownloadError::IoError(library.name, e));
+            }
+        }
+        info!("Writing library '{}' to '{:#?}'", library.name, dist);
+        match fs::write(&dist, contents) {
+            Ok(_) => Ok(()),
+            Err(e) => Err(DownloadError::IoError(library.name, e)),
+        }
+    }))
+    .buffer_unordered(CONCURRENCY)
+    .collect::<Vec<Result<(), DownloadError>>>()
+    .await;
+    results.into_iter().collect()
+}
+
+/// Versions other versions in `versions` inherit from, e.g. Iris and OptiFine profiles built on a loader
+fn get_inherited_versions(versions: &Path) -> Vec<String> {
+    let entries = match fs::read_dir(versions) {
+        Ok(v) => v,
+        Err(_) => return vec![],
+    };
+    entries
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            let json = path.join(format!("{}.json", path.file_name()?.to_str()?));
+            let json: JsonValue = serde_json::from_str(&fs::read_to_string(json).ok()?).ok()?;
+            json.get("inheritsFrom")?.as_str().map(String::from)
+        })
+        .collect()
+}
+
+/// Removes loader versions created by the installer that are no longer used by any installed pack,
+/// launcher profile or other version
+fn clean_loader_versions(launcher: &Launcher) {
+    let backend = launcher.backend();
+    let minecraft_dir = match backend.minecraft_dir() {
+        Some(v) => v,
+        None => return,
+    };
+    let manifests = backend.list_installed().unwrap_or_default();
+    // A pack whose manifest can't be read might still use any of the loaders
+    if let Some(Err(e)) = manifests.iter().find(|x| x.is_err()) {
+        warn!("Not removing unused loader versions: {e}");
+        return;
+    }
+    let versions = minecraft_dir.join("versions");
+    let mut referenced: Vec<String> = manifests
+        .iter()
+        .flatten()
+        .map(|manifest| manifest.loader.version_id())
+        .collect();
+    referenced.extend(get_profile_versions(&minecraft_dir));
+    referenced.extend(get_inherited_versions(&versions));
+    let (keep, remove): (Vec<String>, Vec<String>) = get_tracked_loaders(&minecraft_dir)
+        .into_iter()
+        .partition(|loader| referenced.contains(loader));
+    for loader in &remove {
+        let path = versions.join(loader);
+        if path.is_dir() {
+            info!("Removing unused loader version: {path:#?}");
+            if let Err(e) = fs::remove_dir_all(&path) {
+                error!("Failed to remove loader version '{loader}': {e}");
+            }
+        }
+    }
+    if !remove.is_empty() {
+        set_tracked_loaders(&minecraft_dir, &keep);
     }
-    let resp = http_client
-        .get_async(url)
-        .await
-        .expect("Failed to download loader!")
-        .text()
-        .await
-        .unwrap();
-    fs::create_dir_all(&loader_path).expect("Failed to create loader directory");
-    fs::write(
-        loader_path.join(Path::new(&format!("{}.json", &loader_name))),
-        resp,
-    )
-    .expect("Failed to write loader json");
-    fs::write(
-        loader_path.join(Path::new(&format!("{}.jar", &loader_name))),
-        "",
-    )
-    .expect("Failed to write loader dummy jar");
-    loader_path
 }
 
 async fn download_from_ddl<T: Downloadable + Debug>(
@@ -783,11 +1258,7 @@ async fn download_from_modrinth<T: Downloadable + Debug>(
         Err(e) => return Err(DownloadError::IoError(item.get_name().to_string(), e)),
     }
     for _mod in resp_obj {
-        if &_mod.version_number == item.get_version()
-            && (_mod.loaders.contains(&String::from("minecraft"))
-                || _mod.loaders.contains(&String::from(loader_type))
-                || r#type == "shaderpack")
-        {
+        if &_mod.version_number == item.get_version() && _mod.supports_loader(loader_type, r#type) {
             let content = match match http_client.get_nocache(&_mod.files[0].url).await {
                 Ok(v) => v,
                 Err(e) => return Err(DownloadError::HttpError(item.get_name().to_string(), e)),
@@ -897,45 +1368,8 @@ fn get_app_data() -> PathBuf {
     }
 }
 
-fn get_multimc_folder(multimc: &str) -> Result<PathBuf, String> {
-    let path = match env::consts::OS {
-        "linux" => get_app_data().join(format!(".local/share/{}", multimc)),
-        "windows" | "macos" => get_app_data().join(multimc),
-        _ => panic!("Unsupported os '{}'!", env::consts::OS),
-    };
-    match path.metadata() {
-        Ok(metadata) => {
-            if metadata.is_dir() && path.join("instances").is_dir() {
-                Ok(path)
-            } else {
-                Err(String::from("MultiMC directory is not a valid directory!"))
-            }
-        }
-        Err(e) => Err(e.to_string()),
-    }
-}
-
-fn get_minecraft_folder() -> PathBuf {
-    if env::consts::OS == "macos" {
-        get_app_data().join("minecraft")
-    } else {
-        get_app_data().join(".minecraft")
-    }
-}
-
 fn get_modpack_root(launcher: &Launcher, uuid: &str) -> PathBuf {
-    match launcher {
-        Launcher::Vanilla(root) => {
-            let root = root.join(Path::new(&format!(".WC_OVHL/{}", uuid)));
-            fs::create_dir_all(&root).expect("Failed to create modpack folder");
-            root
-        }
-        Launcher::MultiMC(root) => {
-            let root = root.join(Path::new(&format!("instances/{}/.minecraft", uuid)));
-            fs::create_dir_all(&root).expect("Failed to create modpack folder");
-            root
-        }
-    }
+    launcher.backend().instance_root(uuid)
 }
 
 fn image_to_base64(img: &DynamicImage) -> String {
@@ -946,191 +1380,17 @@ fn image_to_base64(img: &DynamicImage) -> String {
     format!("data:image/png;base64,{}", res_base64)
 }
 
-fn create_launcher_profile(
+async fn create_launcher_profile(
     installer_profile: &InstallerProfile,
     icon_img: Option<DynamicImage>,
 ) -> Result<(), LauncherProfileError> {
-    let now = SystemTime::now();
-    let now: DateTime<Utc> = now.into();
-    let now = now.to_rfc3339();
-    let manifest = &installer_profile.manifest;
-    let modpack_root = get_modpack_root(
-        installer_profile
-            .launcher
-            .as_ref()
-            .expect("No launcher selected!"), // should be impossible
-        &manifest.uuid,
-    );
-    match installer_profile
+    let backend = installer_profile
         .launcher
         .as_ref()
         .expect("Asked to create launcher profile without knowing launcher!") // should be impossible
-    {
-        Launcher::Vanilla(_) => {
-            let icon = if manifest.icon && icon_img.is_some() {
-                image_to_base64(
-                    icon_img
-                        .as_ref()
-                        .unwrap()
-                )
-            } else {
-                String::from("Furnace")
-            };
-            let mut jvm_args = String::new();
-            if manifest.java_args.is_none()
-                && (manifest.max_mem.is_some() || manifest.min_mem.is_some())
-            {
-                jvm_args += "XX:+UnlockExperimentalVMOptions -XX:+UseG1GC -XX:G1NewSizePercent=20 -XX:G1ReservePercent=20 -XX:MaxGCPauseMillis=50 -XX:G1HeapRegionSize=32M";
-            }
-            if let Some(x) = &manifest.java_args {
-                jvm_args += &x
-            }
-            if let Some(x) = manifest.max_mem {
-                jvm_args += &format!(" -Xmx{}M", x)
-            }
-            if let Some(x) = manifest.min_mem {
-                jvm_args += &format!(" -Xms{}M", x)
-            }
-            let profile = LauncherProfile {
-                lastUsed: now.to_string(),
-                lastVersionId: match &manifest.loader.r#type[..] {
-                    "fabric" => format!(
-                        "fabric-loader-{}-{}",
-                        &manifest.loader.version, &manifest.loader.minecraft_version
-                    ),
-                    "quilt" => format!(
-                        "quilt-loader-{}-{}",
-                        &manifest.loader.version, &manifest.loader.minecraft_version
-                    ),
-                    _ => panic!("Invalid loader"),
-                },
-                created: now,
-                name: manifest.name.clone(),
-                icon: Some(icon),
-                r#type: String::from("custom"),
-                gameDir: Some(modpack_root.to_str().unwrap().to_string()),
-                javaDir: None,
-                javaArgs: if jvm_args.is_empty() {
-                    None
-                } else {
-                    Some(jvm_args)
-                },
-                logConfig: None,
-                logConfigIsXML: None,
-                resolution: None,
-            };
-            let lp_file_path = get_minecraft_folder().join(Path::new("launcher_profiles.json"));
-            let mut lp_obj: JsonValue = serde_json::from_str(
-                &fs::read_to_string(&lp_file_path)?,
-            )?;
-            match lp_obj {
-                JsonValue::Object(ref obj) => match obj
-                    .get("profiles")
-                    .ok_or(LauncherProfileError::NoProfiles)?
-                {
-                    JsonValue::Object(_) => {
-                        let profiles = lp_obj.get_mut("profiles").unwrap().as_object_mut().unwrap();
-                        let profile = if profiles.contains_key(&manifest.uuid) {
-                            let mut profile: LauncherProfile = serde_json::from_value(profiles.get(&manifest.uuid).unwrap().clone())?;
-                            profile.lastVersionId = match &manifest.loader.r#type[..] {
-                                "fabric" => format!(
-                                    "fabric-loader-{}-{}",
-                                    &manifest.loader.version, &manifest.loader.minecraft_version
-                                ),
-                                "quilt" => format!(
-                                    "quilt-loader-{}-{}",
-                                    &manifest.loader.version, &manifest.loader.minecraft_version
-                                ),
-                                _ => panic!("Invalid loader"),
-                            };
-                            profile
-                        } else {
-                            profile
-                        };
-                        profiles.insert(manifest.uuid.clone(), serde_json::to_value(profile)?);
-                    },
-                    _ => return Err(LauncherProfileError::ProfilesNotObject),
-                },
-                _ => return Err(LauncherProfileError::RootNotObject),
-            };
-            fs::write(
-                lp_file_path,
-                serde_json::to_string(&lp_obj)?,
-            )?;
-        }
-        Launcher::MultiMC(root) => {
-            let instance_cfg_path = root.join(Path::new(&format!(
-                "instances/{}/instance.cfg",
-                manifest.uuid
-            )));
-            let pack = MMCPack {
-                components: vec![
-                    MMCComponent {
-                        uid: String::from("net.minecraft"),
-                        version: manifest.loader.minecraft_version.to_string(),
-                        cachedVolatile: None,
-                        dependencyOnly: None,
-                        important: Some(true),
-                    },
-                    match &manifest.loader.r#type[..] {
-                        "fabric" => MMCComponent {
-                            uid: String::from("net.fabricmc.fabric-loader"),
-                            version: manifest.loader.version.to_string(),
-                            cachedVolatile: None,
-                            dependencyOnly: None,
-                            important: None,
-                        },
-                        "quilt" => MMCComponent {
-                            uid: String::from("org.quiltmc.quilt-loader"),
-                            version: manifest.loader.version.to_string(),
-                            cachedVolatile: None,
-                            dependencyOnly: None,
-                            important: None,
-                        },
-                        _ => panic!("Invalid loader"),
-                    },
-                ],
-                formatVersion: 1,
-            };
-            fs::write(
-                root.join(Path::new(&format!(
-                    "instances/{}/mmc-pack.json",
-                    manifest.uuid
-                ))),
-                serde_json::to_string(&pack)?,
-            )?;
-            if !instance_cfg_path.exists() {
-                let jvm_args = match manifest.java_args.as_ref() {
-                    Some(v) => format!("\nJvmArgs={}\nOverrideJavaArgs=true", v),
-                    None => String::new(),
-                };
-                let max_mem = match manifest.max_mem {
-                    Some(v) => format!("\nMaxMemAlloc={}", v),
-                    None => String::new(),
-                };
-                let min_mem = match manifest.min_mem {
-                    Some(v) => format!("\nMinMemAlloc={}", v),
-                    None => String::new(),
-                };
-                let override_mem = if max_mem.is_empty() && min_mem.is_empty() {
-                    ""
-                } else {
-                    "\nOverrideMemory=true"
-                };
-                fs::write(
-                    root.join(instance_cfg_path),
-                    format!(
-                        "InstanceType=OneSix\niconKey={}\nname={}{}{}{}{}",
-                        manifest.uuid, manifest.name, max_mem, min_mem, override_mem, jvm_args
-                    ),
-                )?;
-                if manifest.icon {
-                    icon_img.ok_or(LauncherProfileError::IconNotFound)?
-                        .save(root.join(Path::new(&format!("icons/{}.png", manifest.uuid))))?;
-                }
-            }    
-        }
-    };
+        .backend();
+    backend.write_profile(installer_profile, icon_img).await?;
+    launcher::write_install_record(backend.as_ref(), &installer_profile.manifest.uuid)?;
     Ok(())
 }
 
@@ -1159,49 +1419,59 @@ macro_rules! validate_item_path {
     };
 }
 
-fn get_installed_packs(launcher: &Launcher) -> Result<Vec<PackName>, std::io::Error> {
-    let mut packs = vec![];
-    let manifest_paths: Vec<PathBuf> = match launcher {
-        Launcher::Vanilla(root) => {
-            fs::read_dir(root.join(".WC_OVHL/"))?.filter_map(|entry| {
-                let path = entry.ok()?.path().join("manifest.json");
-                if path.exists() {Some(path)} else {None}
-            }).collect()
-        },
-        Launcher::MultiMC(root) => {
-            fs::read_dir(root.join("instances/"))?.filter_map(|entry| {
-                let path = entry.ok()?.path().join(".minecraft/manifest.json");
-                if path.exists() {Some(path)} else {None}
-            }).collect()
-        },
+/// Reads and parses every `<dir>/*/<manifest>` file, folders without one are skipped
+fn get_local_manifests(dir: PathBuf, manifest: &str) -> Vec<Result<Manifest, String>> {
+    let entries = match fs::read_dir(dir) {
+        Ok(v) => v,
+        Err(_) => return vec![],
     };
-    for path in manifest_paths {
-        let manifest: Result<Manifest, serde_json::Error> = serde_json::from_str(&fs::read_to_string(path).unwrap());
-        if let Ok(manifest) = manifest {
-            packs.push(PackName { name: manifest.subtitle, uuid: manifest.uuid })
-        }
-    }
-    
-    Ok(packs)
+    entries
+        .filter_map(|entry| {
+            let path = entry.ok()?.path().join(manifest);
+            let contents = fs::read_to_string(&path).ok()?;
+            Some(
+                migrations::parse_manifest(&contents)
+                    .map_err(|e| format!("Failed to parse '{}': {e}", path.display())),
+            )
+        })
+        .collect()
+}
+
+fn get_installed_packs(launcher: &Launcher) -> Result<Vec<PackName>, std::io::Error> {
+    Ok(launcher
+        .backend()
+        .list_installed()?
+        .into_iter()
+        .filter_map(|manifest| {
+            manifest.map_err(|e| warn!("Skipping installed pack: {e}")).ok()
+        })
+        .map(|manifest| PackName {
+            name: manifest.subtitle,
+            uuid: manifest.uuid,
+        })
+        .collect())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct UninstallOptions {
+    keep_saves: bool,
+    keep_screenshots: bool,
 }
 
-fn uninstall(launcher: &Launcher, uuid: &str) -> Result<(), std::io::Error> {
+fn uninstall(launcher: &Launcher, uuid: &str, options: UninstallOptions) -> Result<(), std::io::Error> {
     info!("Uninstalling modpack: '{uuid}'!");
-    let instance = match launcher {
-        Launcher::Vanilla(root) => {
-            root.join(format!(".WC_OVHL/{uuid}"))
-        }
-        Launcher::MultiMC(root) => {
-            root.join(format!("instances/{uuid}/.minecraft"))
-        }
-    };
-    if instance.is_dir() {
-        fs::remove_dir_all(&instance)?;
-        info!("Removed: {instance:#?}");
-        fs::create_dir(instance)?;
-    } else {
-        error!("Failed to uninstall '{uuid}'");
+    let backend = launcher.backend();
+    let game_dir = backend.game_dir(uuid);
+    let mut keep = vec![];
+    if options.keep_saves {
+        keep.push(game_dir.join("saves"));
+    }
+    if options.keep_screenshots {
+        keep.push(game_dir.join("screenshots"));
     }
+    backend.remove_profile(uuid)?;
+    launcher::remove_install(backend.as_ref(), uuid, &keep)?;
+    clean_loader_versions(launcher);
     let _ = isahc::post(
         "https://tracking.commander07.workers.dev/track",
         format!(
@@ -1224,7 +1494,7 @@ async fn download_helper<T: Downloadable + Debug, F: FnMut() -> () + Clone>(
     progress_callback: F
 ) -> Result<Vec<T>, DownloadError> {
     let results = futures::stream::iter(items.into_iter().map(|item| async {
-        if item.get_path().is_none() && enabled_features.contains(item.get_id()) {
+        if item.get_path().is_none() && item.is_enabled(enabled_features) {
             let path = item
                 .download(modpack_root, loader_type, http_client)
                 .await?;
@@ -1236,12 +1506,13 @@ async fn download_helper<T: Downloadable + Debug, F: FnMut() -> () + Clone>(
                 item.get_version().to_owned(),
                 Some(path),
                 item.get_id().to_owned(),
+                item.get_condition().to_owned(),
                 item.get_authors().to_owned(),
             ))
         } else {
             let item = validate_item_path!(item, modpack_root);
             let path;
-            if !enabled_features.contains(item.get_id()) && item.get_path().is_some() {
+            if !item.is_enabled(enabled_features) && item.get_path().is_some() {
                 info!("Removing: '{:#?}'", item.get_path());
                 let _ = fs::remove_file(item.get_path().as_ref().unwrap());
                 path = None;
@@ -1255,6 +1526,7 @@ async fn download_helper<T: Downloadable + Debug, F: FnMut() -> () + Clone>(
                 item.get_version().to_owned(),
                 path,
                 item.get_id().to_owned(),
+                item.get_condition().to_owned(),
                 item.get_authors().to_owned(),
             ))
         }
@@ -1315,6 +1587,11 @@ async fn download_zip(name: &str, http_client: &CachedHttpClient, url: &str, pat
             Some(outpath) => path.join(outpath),
             None => continue,
         };
+        // Uninstalling removes everything listed in the install record
+        if outpath.file_name().is_some_and(|x| x == launcher::INSTALL_RECORD) {
+            warn!("Skipping '{}' in '{name}'", file.name());
+            continue;
+        }
         if (*file.name()).ends_with('/') {
             fs::create_dir_all(&outpath).unwrap();
         } else {
@@ -1333,7 +1610,7 @@ async fn download_zip(name: &str, http_client: &CachedHttpClient, url: &str, pat
     Ok(files)
 }
 
-async fn install<F: FnMut() -> () + Clone>(installer_profile: &InstallerProfile, mut progress_callback: F) -> Result<(), String> {
+async fn install<F: FnMut() -> () + Clone>(installer_profile: &InstallerProfile, mut progress_callback: F) -> Result<InstallReport, String> {
     info!("Installing modpack");
     info!("installer_profile = {installer_profile:#?}");
     let modpack_root = &get_modpack_root(
@@ -1344,16 +1621,16 @@ async fn install<F: FnMut() -> () + Clone>(installer_profile: &InstallerProfile,
         &installer_profile.manifest.uuid,
     );
     let manifest = &installer_profile.manifest;
+    features::validate(&manifest.features, &installer_profile.enabled_features)?;
     let http_client = &installer_profile.http_client;
-    let minecraft_folder = get_minecraft_folder();
-    let loader_future = match installer_profile.launcher.as_ref().unwrap() {
-        Launcher::Vanilla(_) => Some(manifest.loader.download(
-            &minecraft_folder,
-            &manifest.loader.r#type,
-            http_client,
-        )),
-        Launcher::MultiMC(_) => None,
-    };
+    let backend = installer_profile.launcher.as_ref().unwrap().backend();
+    if let Err(e) = launcher::restore_kept(backend.as_ref(), &manifest.uuid) {
+        error!("Failed to restore kept files: {e}");
+    }
+    let minecraft_dir = backend.minecraft_dir();
+    let loader_future = minecraft_dir.as_ref().map(|minecraft_dir| {
+        manifest.loader.download(minecraft_dir, &manifest.loader.r#type, http_client)
+    });
     let mods_w_path = match download_helper(
         manifest.mods.clone(),
         &installer_profile.enabled_features,
@@ -1367,6 +1644,44 @@ async fn install<F: FnMut() -> () + Clone>(installer_profile: &InstallerProfile,
         Ok(v) => v,
         Err(e) => return Err(e.to_string()),
     };
+    let java_version =
+        java::get_required_java_version(&manifest.loader.minecraft_version, http_client).await;
+    if let Err(e) = &java_version {
+        warn!("Failed to get required java version, not checking mods for it: {e}");
+    }
+    let java_version = java_version.ok();
+    let mut report = InstallReport::default();
+    for r#mod in &mods_w_path {
+        let metadata = match r#mod.get_path() {
+            Some(path) => mod_metadata::read_mod_metadata(path),
+            None => None,
+        };
+        if let Some(metadata) = metadata {
+            for reason in mod_metadata::check_compatibility(&metadata, &manifest.loader, java_version) {
+                warn!("'{}' is incompatible: {reason}", r#mod.name);
+                report
+                    .incompatible_mods
+                    .push(format!("{} ({}) {reason}", r#mod.name, metadata.id));
+            }
+        }
+    }
+    let installed_mods = mod_metadata::scan_mods(&modpack_root.join("mods"));
+    let tracked: Vec<&PathBuf> = mods_w_path
+        .iter()
+        .filter_map(|x| x.get_path().as_ref())
+        .collect();
+    for (id, files) in mod_metadata::find_duplicates(&installed_mods) {
+        warn!("Found duplicate mod '{id}': {files:#?}");
+        report.duplicate_mods.push(DuplicateMod {
+            id,
+            untracked: files.iter().filter(|x| !tracked.contains(x)).cloned().collect(),
+            files,
+        });
+    }
+    for conflict in mod_metadata::find_conflicts(&installed_mods) {
+        warn!("Found conflicting mods: {conflict}");
+        report.conflicting_mods.push(conflict);
+    }
     let shaderpacks_w_path = match download_helper(
         manifest.shaderpacks.clone(),
         &installer_profile.enabled_features,
@@ -1401,17 +1716,6 @@ async fn install<F: FnMut() -> () + Clone>(installer_profile: &InstallerProfile,
         },
         None => HashMap::new(),
     };
-    for inc in &inc_files {
-        if !installer_profile
-            .enabled_features
-            .contains(&inc.0.replace(".zip", ""))
-        {
-            for file in &inc.1.files {
-                info!("Removing: '{file}'");
-                let _ = fs::remove_file(file);
-            }
-        }
-    }
     if !manifest.include.is_empty() {
         // Include files exist
         let release: GithubRelease = serde_json::from_str(
@@ -1439,7 +1743,11 @@ async fn install<F: FnMut() -> () + Clone>(installer_profile: &InstallerProfile,
         .expect("Failed to parse hash pairs!");
         let mut downloaded_assets = vec![];
         for inc in &manifest.include {
-            if !installer_profile.enabled_features.contains(&inc.id) {
+            if !features::is_enabled(
+                &inc.id,
+                &inc.condition,
+                &installer_profile.enabled_features,
+            ) {
                 continue;
             }
             'a: for asset in &release.assets {
@@ -1483,45 +1791,87 @@ async fn install<F: FnMut() -> () + Clone>(installer_profile: &InstallerProfile,
                 }
             }
         }
-
-        if let Some(includes) = manifest.remote_include.clone() {
-            for include in includes {
-                if !installer_profile.enabled_features.contains(&include.id) {
-                    continue;
-                }
-                let name = include.name.unwrap_or(include.location.clone());
-                let outpath = if let Some(path) = include.path {
-                    modpack_root.join(path)
-                } else {
-                    modpack_root.to_owned()
-                };
-                match inc_files.get(&include.location) {
-                    Some(local_inc) => {
-                        if local_inc.md5 == include.version {
-                            included_files.insert(include.location, local_inc.to_owned());
-                            info!("Skipping '{}' as it is already downloaded", name);
-                            continue;
-                        } else {
-                            for file in &local_inc.files {
-                                let path = Path::new(file);
-                                assert!(
-                                    path.starts_with(&outpath),
-                                    "Local include path was not located in modpack root!"
-                                );
-                                let _ = fs::remove_file(path);
-                            }
+    }
+    if let Some(includes) = manifest.remote_include.clone() {
+        for include in includes {
+            if !features::is_enabled(
+                &include.id,
+                &include.condition,
+                &installer_profile.enabled_features,
+            ) {
+                continue;
+            }
+            let name = include.name.unwrap_or(include.location.clone());
+            let outpath = if let Some(path) = include.path {
+                modpack_root.join(path)
+            } else {
+                modpack_root.to_owned()
+            };
+            match inc_files.get(&include.location) {
+                Some(local_inc) => {
+                    if local_inc.md5 == include.version {
+                        included_files.insert(include.location, local_inc.to_owned());
+                        info!("Skipping '{}' as it is already downloaded", name);
+                        continue;
+                    } else {
+                        for file in &local_inc.files {
+                            let path = Path::new(file);
+                            assert!(
+                                path.starts_with(&outpath),
+                                "Local include path was not located in modpack root!"
+                            );
+                            let _ = fs::remove_file(path);
                         }
                     }
-                    None => (),
-                };
-                let files = match download_zip(&name, http_client, &include.location, &outpath).await {
-                    Ok(v) => v,
-                    Err(e) => return Err(format!("Failed to download include: {:#?}", e)),
-                };
-                included_files.insert(name.clone(), Included { md5: include.version, files });
-                info!("'{}' is now installed", name);
-                progress_callback();
-            }
+                }
+                None => (),
+            };
+            let files = match download_zip(&name, http_client, &include.location, &outpath).await {
+                Ok(v) => v,
+                Err(e) => return Err(format!("Failed to download include: {:#?}", e)),
+            };
+            included_files.insert(include.location, Included { md5: include.version, files });
+            info!("'{}' is now installed", name);
+            progress_callback();
+        }
+    }
+    // Only includes whose feature or condition is no longer enabled, or that were removed from the
+    // manifest, are removed. Enabled ones that weren't installed by this run (e.g. because the
+    // asset is missing from the release) keep their files and stay recorded
+    let enabled_includes: Vec<String> = manifest
+        .include
+        .iter()
+        .filter(|x| features::is_enabled(&x.id, &x.condition, &installer_profile.enabled_features))
+        .map(|x| x.id.clone() + ".zip")
+        .chain(
+            manifest
+                .remote_include
+                .iter()
+                .flatten()
+                .filter(|x| {
+                    features::is_enabled(&x.id, &x.condition, &installer_profile.enabled_features)
+                })
+                .map(|x| x.location.clone()),
+        )
+        .collect();
+    for (name, inc) in &inc_files {
+        if !included_files.contains_key(name) && enabled_includes.contains(name) {
+            included_files.insert(name.clone(), inc.clone());
+        }
+    }
+    let recorded: Vec<&String> = included_files.values().flat_map(|x| &x.files).collect();
+    for (name, inc) in &inc_files {
+        if included_files.contains_key(name) {
+            continue;
+        }
+        for file in inc.files.iter().filter(|x| !recorded.contains(x)) {
+            let path = Path::new(file);
+            assert!(
+                path.starts_with(modpack_root),
+                "Local include path was not located in modpack root!"
+            );
+            info!("Removing: '{file}'");
+            let _ = fs::remove_file(path);
         }
     }
     let local_manifest = Manifest {
@@ -1575,15 +1925,32 @@ async fn install<F: FnMut() -> () + Clone>(installer_profile: &InstallerProfile,
     } else {
         None
     };
-    match create_launcher_profile(&installer_profile, icon_img) {
+    let mut installer_profile = installer_profile.clone();
+    if installer_profile.managed_java
+        && installer_profile.java_path.is_none()
+        && matches!(installer_profile.launcher, Some(Launcher::MultiMC(_)))
+    {
+        let version = match java_version {
+            Some(v) => v,
+            None => {
+                java::get_required_java_version(&manifest.loader.minecraft_version, http_client)
+                    .await?
+            }
+        };
+        installer_profile.java_path =
+            Some(java::download_java(version, http_client).await?);
+    }
+    match create_launcher_profile(&installer_profile, icon_img).await {
         Ok(_) => {}
         Err(e) => return Err(e.to_string()),
     };
-    if loader_future.is_some() {
-        loader_future.unwrap().await;
+    if let Some(loader_future) = loader_future {
+        if let Err(e) = loader_future.await {
+            return Err(e.to_string());
+        }
     }
     info!("Installed modpack!");
-    Ok(())
+    Ok(report)
 }
 
 fn remove_old_items<T: Downloadable + PartialEq + Clone + Debug>(
@@ -1629,7 +1996,7 @@ fn remove_old_items<T: Downloadable + PartialEq + Clone + Debug>(
 
 // Why haven't I split this into multiple files? That's a good question. I forgot, and I can't be bothered to do it now.
 // TODO(Split project into multiple files to improve maintainability)
-async fn update<F: FnMut() -> () + Clone>(installer_profile: &InstallerProfile, progress_callback: F)-> Result<(), String> {
+async fn update<F: FnMut() -> () + Clone>(installer_profile: &InstallerProfile, progress_callback: F)-> Result<InstallReport, String> {
     info!("Updating modpack");
     info!("installer_profile = {installer_profile:#?}");
     let local_manifest: Manifest = match fs::read_to_string(
@@ -1642,7 +2009,7 @@ async fn update<F: FnMut() -> () + Clone>(installer_profile: &InstallerProfile,
         )
         .join(Path::new("manifest.json")),
     ) {
-        Ok(contents) => match serde_json::from_str(&contents) {
+        Ok(contents) => match migrations::parse_manifest(&contents) {
             Ok(parsed) => parsed,
             Err(err) => panic!("Failed to parse local manifest: {}", err),
         },
@@ -1663,6 +2030,7 @@ async fn update<F: FnMut() -> () + Clone>(installer_profile: &InstallerProfile,
     update_profile.manifest.resourcepacks = new_resourcepacks;
     let e = install(&update_profile, progress_callback).await;
     if e.is_ok() {
+        clean_loader_versions(installer_profile.launcher.as_ref().unwrap());
         info!("Updated modpack");
     } else {
         error!("Failed to update modpack: {e:#?}")
@@ -1673,7 +2041,15 @@ async fn update<F: FnMut() -> () + Clone>(installer_profile: &InstallerProfile,
 fn get_launcher(string_representation: &str) -> Result<Launcher, String> {
     let mut launcher = string_representation.split('-').collect::<Vec<_>>();
     match *launcher.first().unwrap() {
-        "vanilla" => Ok(Launcher::Vanilla(get_app_data())),
+        "vanilla" if launcher.len() == 1 => Ok(Launcher::Vanilla(get_minecraft_folder())),
+        "vanilla" => {
+            let root = PathBuf::from(launcher.split_off(1).join("-"));
+            if root.is_dir() {
+                Ok(Launcher::Vanilla(root))
+            } else {
+                Err(String::from("Minecraft directory is not a valid directory!"))
+            }
+        }
         "multimc" => {
             let data_dir = get_multimc_folder(
                 launcher
@@ -1685,6 +2061,9 @@ fn get_launcher(string_representation: &str) -> Result<Launcher, String> {
                 Err(e) => Err(e),
             }
         }
+        "atlauncher" => launcher::get_atlauncher_folder().map(Launcher::ATLauncher),
+        "modrinth" => launcher::get_modrinth_folder().map(Launcher::Modrinth),
+        "gdlauncher" => launcher::get_gdlauncher_folder().map(Launcher::GDLauncher),
         "custom" => {
             let data_dir = PathBuf::from(launcher.split_off(1).join("-"));
             match data_dir.metadata() {
@@ -1701,7 +2080,40 @@ fn get_launcher(string_representation: &str) -> Result<Launcher, String> {
     }
 }
 
+/// Release builds on Windows use the GUI subsystem, which doesn't get a console, so the command line
+/// modes attach to the console of the shell they were started from to print their output
+#[cfg(windows)]
+fn attach_console() {
+    const ATTACH_PARENT_PROCESS: u32 = u32::MAX;
+    extern "system" {
+        fn AttachConsole(process_id: u32) -> i32;
+    }
+    // Fails when there is no parent console or one is already attached, neither of which is a problem
+    unsafe {
+        AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+}
+
+#[cfg(not(windows))]
+fn attach_console() {}
+
 fn main() {
+    if env::args().any(|x| x == "--manifest-schema" || x == "--lint") {
+        attach_console();
+    }
+    // Used to regenerate `manifest.schema.json`
+    if let Some(path) = env::args().skip_while(|x| x != "--manifest-schema").nth(1) {
+        fs::write(
+            path,
+            serde_json::to_string_pretty(&schema::manifest_schema()).unwrap(),
+        )
+        .expect("Failed to write manifest schema!");
+        return;
+    }
+    if let Some(branch) = env::args().skip_while(|x| x != "--lint").nth(1) {
+        let file = env::args().skip_while(|x| x != "--lint").nth(2);
+        std::process::exit(lint::run(&branch, file.as_deref().map(Path::new)));
+    }
     fs::create_dir_all(get_app_data().join(".WC_OVHL/")).expect("Failed to create config dir!");
     CombinedLogger::init(vec![
         TermLogger::new(
@@ -1731,6 +2143,7 @@ fn main() {
     info!("Installer version: {}", env!("CARGO_PKG_VERSION"));
     let platform_info = PlatformInfo::new().expect("Unable to determine platform info");
     info!("System information:\n\tSysname: {}\n\tRelease: {}\n\tVersion: {}\n\tArchitecture: {}\n\tOsname: {}",platform_info.sysname().to_string_lossy(), platform_info.release().to_string_lossy(), platform_info.version().to_string_lossy(), platform_info.machine().to_string_lossy(), platform_info.osname().to_string_lossy());
+    info!("Platform: {:?}", platform::current());
     let icon = image::load_from_memory(include_bytes!("assets/icon.png")).unwrap();
     let branches: Vec<GithubBranch> = serde_json::from_str(
         build_http_client()
@@ -1750,6 +2163,10 @@ fn main() {
         config = Config {
             launcher: String::from("vanilla"),
             first_launch: Some(true),
+            java: HashMap::new(),
+            managed_java: false,
+            instance_names: HashMap::new(),
+            launcher_executables: HashMap::new(),
         };
         fs::write(&config_path, serde_json::to_vec(&config).unwrap())
             .expect("Failed to write config!");
@@ -1775,9 +2192,13 @@ fn main() {
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[allow(clippy::enum_variant_names)]
 enum Launcher {
     Vanilla(PathBuf),
     MultiMC(PathBuf),
+    ATLauncher(PathBuf),
+    Modrinth(PathBuf),
+    GDLauncher(PathBuf),
 }
 
 impl Display for Launcher {
@@ -1785,10 +2206,38 @@ impl Display for Launcher {
         match self {
             Launcher::Vanilla(_) => write!(f, "Vanilla"),
             Launcher::MultiMC(_) => write!(f, "MultiMC"),
+            Launcher::ATLauncher(_) => write!(f, "ATLauncher"),
+            Launcher::Modrinth(_) => write!(f, "Modrinth App"),
+            Launcher::GDLauncher(_) => write!(f, "GDLauncher"),
         }
     }
 }
 
+/// Several jars in the mods folder declaring the same mod id
+#[derive(Debug, Clone, PartialEq)]
+struct DuplicateMod {
+    id: String,
+    files: Vec<PathBuf>,
+    /// Copies that aren't part of the modpack, e.g. older versions or manually added jars
+    untracked: Vec<PathBuf>,
+}
+
+/// Problems found during install that don't prevent the pack from being installed
+#[derive(Debug, Clone, Default, PartialEq)]
+struct InstallReport {
+    incompatible_mods: Vec<String>,
+    duplicate_mods: Vec<DuplicateMod>,
+    conflicting_mods: Vec<String>,
+}
+
+impl InstallReport {
+    fn is_empty(&self) -> bool {
+        self.incompatible_mods.is_empty()
+            && self.duplicate_mods.is_empty()
+            && self.conflicting_mods.is_empty()
+    }
+}
+
 #[derive(Debug, Clone)]
 struct InstallerProfile {
     manifest: Manifest,
@@ -1800,6 +2249,33 @@ struct InstallerProfile {
     enabled_features: Vec<String>,
     launcher: Option<Launcher>,
     local_manifest: Option<Manifest>,
+    java_warning: Option<String>,
+    /// Explanations for features that were changed to make the selection valid
+    feature_notices: Vec<String>,
+    /// Java selected in the settings, `None` leaves it up to the launcher
+    java_path: Option<PathBuf>,
+    managed_java: bool,
+    /// Instance name selected in the settings, `None` uses the manifest's
+    instance_name: Option<String>,
+}
+
+impl InstallerProfile {
+    /// Name the pack is shown with in the launcher
+    fn instance_name(&self) -> String {
+        self.instance_name
+            .clone()
+            .unwrap_or_else(|| self.manifest.instance_name())
+    }
+
+    /// The manifest's `java_args` or the first of its overrides that applies
+    fn java_args(&self) -> Option<&String> {
+        self.manifest
+            .java_args_overrides
+            .iter()
+            .find(|x| x.condition.is_met(&self.enabled_features))
+            .map(|x| &x.java_args)
+            .or(self.manifest.java_args.as_ref())
+    }
 }
 
 async fn init(
@@ -1815,28 +2291,37 @@ async fn init(
         Ok(val) => val,
         Err(e) => return Err(e.to_string()),
     };
-    let manifest: Manifest =
+    let manifest: JsonValue =
         match serde_json::from_str(manifest_resp.text().await.unwrap().as_str()) {
             Ok(val) => val,
             Err(e) => return Err(e.to_string()),
         };
-
-    // Its not guaranteed that a manifest with a different version manages to parse however we handle parsing failures and therefore we should be fine to just return an error here
-    if CURRENT_MANIFEST_VERSION != manifest.manifest_version {
-        return Err(format!(
-            "Unsupported manifest version '{}'!",
-            manifest.manifest_version
-        ));
-    }
+    migrations::check_installer_version(&manifest)?;
+    schema::validate_manifest(&manifest)?;
+    let manifest = migrations::parse(manifest)?;
     let modpack_root = get_modpack_root(&launcher, &manifest.uuid);
     let mut installed = modpack_root.join(Path::new("manifest.json")).exists();
-    let local_manifest: Option<Result<Manifest, serde_json::Error>> = if installed {
+    let mut feature_notices = vec![];
+    let local_manifest: Option<Result<Manifest, String>> = if installed {
         let local_manifest_content =
             match fs::read_to_string(modpack_root.join(Path::new("manifest.json"))) {
                 Ok(val) => val,
                 Err(e) => return Err(e.to_string()),
             };
-        Some(serde_json::from_str(&local_manifest_content))
+        let mut local_manifest = migrations::parse_manifest(&local_manifest_content);
+        if let Ok(local) = &mut local_manifest {
+            if let Err(e) = features::validate(&manifest.features, &local.enabled_features) {
+                warn!("Repairing invalid installed features: {e}");
+                let (repaired, changes) =
+                    features::repair(&manifest.features, &local.enabled_features);
+                local.enabled_features = repaired;
+                feature_notices.push(String::from(
+                    "The installed features are no longer valid for this version and were adjusted.",
+                ));
+                feature_notices.extend(changes);
+            }
+        }
+        Some(local_manifest)
     } else {
         installed = false;
         None
@@ -1849,6 +2334,13 @@ async fn init(
     } else {
         false
     };
+    let java_warning = java::check_java(
+        &launcher,
+        &manifest.uuid,
+        &manifest.loader.minecraft_version,
+        &http_client,
+    )
+    .await;
     let mut enabled_features = vec![default_id()];
     if !installed {
         for feat in &manifest.features {
@@ -1856,6 +2348,15 @@ async fn init(
                 enabled_features.push(feat.id.clone());
             }
         }
+        if let Err(e) = features::validate(&manifest.features, &enabled_features) {
+            warn!("Repairing invalid default features: {e}");
+            let (repaired, changes) = features::repair(&manifest.features, &enabled_features);
+            enabled_features = repaired;
+            feature_notices.push(String::from(
+                "The default features of this modpack conflict with each other and were adjusted.",
+            ));
+            feature_notices.extend(changes);
+        }
     }
     Ok(InstallerProfile {
         manifest,
@@ -1871,5 +2372,109 @@ async fn init(
         } else {
             None
         },
+        java_warning,
+        feature_notices,
+        java_path: None,
+        managed_java: false,
+        instance_name: None,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::launcher::tests::{test_manifest, write_local_manifest, TempDir, UUID};
+
+    fn create_loader(minecraft_dir: &Path, name: &str, inherits_from: Option<&str>) {
+        let path = minecraft_dir.join(format!("versions/{name}"));
+        fs::create_dir_all(&path).unwrap();
+        let mut json = serde_json::json!({ "id": name });
+        if let Some(inherits_from) = inherits_from {
+            json["inheritsFrom"] = serde_json::json!(inherits_from);
+        }
+        fs::write(path.join(format!("{name}.json")), json.to_string()).unwrap();
+        fs::write(path.join(format!("{name}.jar")), "").unwrap();
+    }
+
+    fn setup_vanilla(dir: &TempDir) -> Launcher {
+        let minecraft_dir = dir.path().to_path_buf();
+        fs::write(
+            minecraft_dir.join("launcher_profiles.json"),
+            serde_json::json!({
+                "profiles": { "user": { "lastVersionId": "fabric-loader-0.14.0-1.20.1" } }
+            })
+            .to_string(),
+        )
+        .unwrap();
+        for loader in [
+            "fabric-loader-0.15.11-1.20.1",
+            "fabric-loader-0.14.0-1.20.1",
+            "fabric-loader-0.13.0-1.19.4",
+            "quilt-loader-0.20.0-1.20.1",
+        ] {
+            create_loader(&minecraft_dir, loader, None);
+        }
+        create_loader(
+            &minecraft_dir,
+            "iris-fabric-loader-0.13.0-1.19.4",
+            Some("fabric-loader-0.13.0-1.19.4"),
+        );
+        set_tracked_loaders(
+            &minecraft_dir,
+            &[
+                String::from("fabric-loader-0.15.11-1.20.1"),
+                String::from("fabric-loader-0.14.0-1.20.1"),
+                String::from("fabric-loader-0.13.0-1.19.4"),
+                String::from("quilt-loader-0.20.0-1.20.1"),
+            ],
+        );
+        Launcher::Vanilla(minecraft_dir)
+    }
+
+    #[test]
+    fn clean_loader_versions_removes_unused_tracked_loaders() {
+        let dir = TempDir::new();
+        let launcher = setup_vanilla(&dir);
+        write_local_manifest(&launcher.backend().game_dir(UUID), &test_manifest());
+        clean_loader_versions(&launcher);
+        let versions = dir.path().join("versions");
+        // Used by the installed pack, a launcher profile and another version
+        assert!(versions.join("fabric-loader-0.15.11-1.20.1").is_dir());
+        assert!(versions.join("fabric-loader-0.14.0-1.20.1").is_dir());
+        assert!(versions.join("fabric-loader-0.13.0-1.19.4").is_dir());
+        assert!(!versions.join("quilt-loader-0.20.0-1.20.1").exists());
+        assert!(versions.join("iris-fabric-loader-0.13.0-1.19.4").is_dir());
+        assert_eq!(
+            get_tracked_loaders(dir.path()),
+            [
+                "fabric-loader-0.15.11-1.20.1",
+                "fabric-loader-0.14.0-1.20.1",
+                "fabric-loader-0.13.0-1.19.4",
+            ]
+        );
+    }
+
+    #[test]
+    fn clean_loader_versions_keeps_untracked_loaders() {
+        let dir = TempDir::new();
+        let launcher = setup_vanilla(&dir);
+        fs::remove_file(get_loader_record_path(dir.path())).unwrap();
+        create_loader(dir.path(), "fabric-loader-0.16.0-1.21", None);
+        clean_loader_versions(&launcher);
+        let versions = dir.path().join("versions");
+        assert!(versions.join("quilt-loader-0.20.0-1.20.1").is_dir());
+        assert!(versions.join("fabric-loader-0.16.0-1.21").is_dir());
+        assert!(get_tracked_loaders(dir.path()).is_empty());
+    }
+
+    #[test]
+    fn clean_loader_versions_skips_unreadable_manifests() {
+        let dir = TempDir::new();
+        let launcher = setup_vanilla(&dir);
+        let game_dir = launcher.backend().game_dir(UUID);
+        fs::create_dir_all(&game_dir).unwrap();
+        fs::write(game_dir.join("manifest.json"), "{").unwrap();
+        clean_loader_versions(&launcher);
+        assert!(dir.path().join("versions/quilt-loader-0.20.0-1.20.1").is_dir());
+    }
+}
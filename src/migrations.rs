@@ -0,0 +1,215 @@
+// Reads manifests written for older manifest versions as their own types and migrates them to the
+// current `Manifest`
+use std::collections::HashMap;
+
+use log::info;
+use semver::Version;
+use serde::Deserialize;
+use serde_json::Value as JsonValue;
+
+use crate::{
+    default_enabled_features, Feature, Include, Included, Loader, Manifest, Mod, RemoteInclude,
+    Resourcepack, Shaderpack, CURRENT_MANIFEST_VERSION,
+};
+
+/// Version 3 of the manifest, the oldest version the installer can read
+///
+/// The item types only gained optional fields since, so they are shared with the current version
+#[derive(Debug, Deserialize)]
+struct ManifestV3 {
+    modpack_version: String,
+    name: String,
+    subtitle: String,
+    tab_group: Option<usize>,
+    tab_title: Option<String>,
+    tab_color: Option<String>,
+    tab_background: Option<String>,
+    tab_primary_font: Option<String>,
+    tab_secondary_font: Option<String>,
+    settings_background: Option<String>,
+    popup_title: Option<String>,
+    popup_contents: Option<String>,
+    description: String,
+    icon: bool,
+    uuid: String,
+    loader: Loader,
+    mods: Vec<Mod>,
+    shaderpacks: Vec<Shaderpack>,
+    resourcepacks: Vec<Resourcepack>,
+    remote_include: Option<Vec<RemoteInclude>>,
+    include: Vec<Include>,
+    features: Vec<Feature>,
+    #[serde(default = "default_enabled_features")]
+    enabled_features: Vec<String>,
+    included_files: Option<HashMap<String, Included>>,
+    source: Option<String>,
+    installer_path: Option<String>,
+    max_mem: Option<i32>,
+    min_mem: Option<i32>,
+    java_args: Option<String>,
+}
+
+/// Version 4 added `min_installer_version`, `java_args_overrides`, `instance_name` and
+/// `instance_group`, which all default to being unset
+impl From<ManifestV3> for Manifest {
+    fn from(v3: ManifestV3) -> Self {
+        Manifest {
+            manifest_version: 4,
+            min_installer_version: None,
+            modpack_version: v3.modpack_version,
+            name: v3.name,
+            subtitle: v3.subtitle,
+            tab_group: v3.tab_group,
+            tab_title: v3.tab_title,
+            tab_color: v3.tab_color,
+            tab_background: v3.tab_background,
+            tab_primary_font: v3.tab_primary_font,
+            tab_secondary_font: v3.tab_secondary_font,
+            settings_background: v3.settings_background,
+            popup_title: v3.popup_title,
+            popup_contents: v3.popup_contents,
+            description: v3.description,
+            icon: v3.icon,
+            uuid: v3.uuid,
+            loader: v3.loader,
+            mods: v3.mods,
+            shaderpacks: v3.shaderpacks,
+            resourcepacks: v3.resourcepacks,
+            remote_include: v3.remote_include,
+            include: v3.include,
+            features: v3.features,
+            enabled_features: v3.enabled_features,
+            included_files: v3.included_files,
+            source: v3.source,
+            installer_path: v3.installer_path,
+            max_mem: v3.max_mem,
+            min_mem: v3.min_mem,
+            java_args: v3.java_args,
+            java_args_overrides: vec![],
+            instance_name: None,
+            instance_group: None,
+        }
+    }
+}
+
+/// Parses versions like `1.6`, `v1.6.0` or `1.6.0-beta.1`, a missing minor or patch version is `0`
+fn parse_version(version: &str) -> Result<Version, String> {
+    let version = version.trim_start_matches('v');
+    let (core, suffix) = version.split_at(version.find(['-', '+']).unwrap_or(version.len()));
+    let missing = 2usize.saturating_sub(core.matches('.').count());
+    Version::parse(&format!("{core}{}{suffix}", ".0".repeat(missing)))
+        .map_err(|e| format!("Invalid version '{version}': {e}"))
+}
+
+/// Fails if the manifest's `min_installer_version` is newer than this installer
+///
+/// Only remote manifests are checked, local copies were installed by an installer that supported them
+pub(crate) fn check_installer_version(manifest: &JsonValue) -> Result<(), String> {
+    let current = env!("CARGO_PKG_VERSION");
+    let required = match manifest
+        .get("min_installer_version")
+        .and_then(|x| x.as_str())
+    {
+        Some(v) => v,
+        None => return Ok(()),
+    };
+    if parse_version(required)? > parse_version(current)? {
+        return Err(format!(
+            "This modpack requires installer version {required} or newer but you are using version {current}, please download the latest installer!"
+        ));
+    }
+    Ok(())
+}
+
+/// Parses a manifest of any supported version and migrates it to the current manifest version
+pub(crate) fn parse(manifest: JsonValue) -> Result<Manifest, String> {
+    let version = manifest
+        .get("manifest_version")
+        .and_then(|x| x.as_i64())
+        .ok_or("Manifest is missing 'manifest_version'")?;
+    if version > CURRENT_MANIFEST_VERSION as i64 {
+        return Err(format!(
+            "Unsupported manifest version '{version}', please download the latest installer!"
+        ));
+    }
+    match version {
+        3 => {
+            info!("Migrating manifest from version 3 to 4");
+            let v3: ManifestV3 = serde_json::from_value(manifest).map_err(|e| e.to_string())?;
+            Ok(v3.into())
+        }
+        4 => serde_json::from_value(manifest).map_err(|e| e.to_string()),
+        _ => Err(format!("Unsupported manifest version '{version}'!")),
+    }
+}
+
+/// Parses a manifest of any supported version, used for the local copies saved by older installers
+pub(crate) fn parse_manifest(contents: &str) -> Result<Manifest, String> {
+    parse(serde_json::from_str(contents).map_err(|e| e.to_string())?)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn parse_version_pads_components() {
+        assert_eq!(parse_version("1.2").unwrap(), Version::new(1, 2, 0));
+        assert_eq!(parse_version("v1").unwrap(), Version::new(1, 0, 0));
+        assert_eq!(
+            parse_version("1.2.0").unwrap(),
+            parse_version("1.2").unwrap()
+        );
+        assert!(parse_version("1.6-beta.1").unwrap() < parse_version("1.6").unwrap());
+        assert!(parse_version("1.10").unwrap() > parse_version("1.9.9").unwrap());
+        assert!(parse_version("latest").is_err());
+    }
+
+    #[test]
+    fn check_installer_version_compares_versions() {
+        let required = |version: &str| json!({ "min_installer_version": version });
+        assert!(check_installer_version(&json!({})).is_ok());
+        assert!(check_installer_version(&required("1.0")).is_ok());
+        assert!(check_installer_version(&required(env!("CARGO_PKG_VERSION"))).is_ok());
+        assert!(check_installer_version(&required("999.0")).is_err());
+        assert!(check_installer_version(&required("not a version")).is_err());
+    }
+
+    #[test]
+    fn parse_migrates_v3() {
+        let mut v3 = serde_json::to_value(crate::launcher::tests::test_manifest()).unwrap();
+        let obj = v3.as_object_mut().unwrap();
+        for field in [
+            "min_installer_version",
+            "java_args_overrides",
+            "instance_name",
+            "instance_group",
+        ] {
+            obj.remove(field);
+        }
+        obj.insert(String::from("manifest_version"), json!(3));
+        obj.insert(String::from("java_args"), json!("-XX:+UseZGC"));
+        let manifest = parse(v3).unwrap();
+        assert_eq!(manifest.manifest_version, CURRENT_MANIFEST_VERSION);
+        assert_eq!(manifest.java_args.as_deref(), Some("-XX:+UseZGC"));
+        assert!(manifest.java_args_overrides.is_empty());
+        assert_eq!(manifest.instance_name, None);
+    }
+
+    #[test]
+    fn parse_rejects_unknown_versions() {
+        let manifest = |version: i32| {
+            let mut manifest =
+                serde_json::to_value(crate::launcher::tests::test_manifest()).unwrap();
+            manifest["manifest_version"] = json!(version);
+            manifest
+        };
+        assert!(parse(manifest(CURRENT_MANIFEST_VERSION)).is_ok());
+        assert!(parse(manifest(2)).is_err());
+        assert!(parse(manifest(CURRENT_MANIFEST_VERSION + 1))
+            .unwrap_err()
+            .contains("download the latest installer"));
+    }
+}
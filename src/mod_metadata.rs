@@ -0,0 +1,376 @@
+use std::{
+    cmp::Ordering,
+    collections::HashMap,
+    fs::{self, File},
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+use log::warn;
+use serde_json::Value as JsonValue;
+
+use crate::Loader;
+
+/// The parts of `fabric.mod.json`/`quilt.mod.json` the installer cares about
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ModMetadata {
+    pub id: String,
+    pub version: String,
+    /// Dependency id to version predicates, any of which has to match
+    pub depends: HashMap<String, Vec<String>>,
+    /// Mod id to version predicates of mods this mod can't be used with
+    pub breaks: HashMap<String, Vec<String>>,
+    /// Additional mod ids this mod can satisfy dependencies for
+    pub provides: Vec<String>,
+}
+
+fn predicates(value: &JsonValue) -> Vec<String> {
+    match value {
+        JsonValue::String(x) => vec![x.clone()],
+        JsonValue::Array(x) => x.iter().flat_map(predicates).collect(),
+        // quilt allows '{ "any": [...] }'
+        JsonValue::Object(x) => x.get("any").map(predicates).unwrap_or_default(),
+        _ => vec![],
+    }
+}
+
+fn parse_fabric_relations(json: &JsonValue, key: &str) -> HashMap<String, Vec<String>> {
+    match json.get(key) {
+        Some(JsonValue::Object(x)) => x.iter().map(|(k, v)| (k.clone(), predicates(v))).collect(),
+        _ => HashMap::new(),
+    }
+}
+
+fn parse_fabric(json: &JsonValue) -> Option<ModMetadata> {
+    let provides = match json.get("provides") {
+        Some(JsonValue::Array(x)) => x
+            .iter()
+            .filter_map(|x| x.as_str().map(str::to_string))
+            .collect(),
+        _ => vec![],
+    };
+    Some(ModMetadata {
+        id: json.get("id")?.as_str()?.to_string(),
+        version: json.get("version")?.as_str()?.to_string(),
+        depends: parse_fabric_relations(json, "depends"),
+        breaks: parse_fabric_relations(json, "breaks"),
+        provides,
+    })
+}
+
+fn parse_quilt_relations(loader: &JsonValue, key: &str) -> HashMap<String, Vec<String>> {
+    let mut relations = HashMap::new();
+    if let Some(JsonValue::Array(entries)) = loader.get(key) {
+        for entry in entries {
+            match entry {
+                JsonValue::String(id) => {
+                    relations.insert(id.clone(), vec![String::from("*")]);
+                }
+                JsonValue::Object(x) => {
+                    if let Some(id) = x.get("id").and_then(|x| x.as_str()) {
+                        let versions = x.get("versions").map(predicates).unwrap_or_default();
+                        relations.insert(id.to_string(), versions);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    relations
+}
+
+fn parse_quilt(json: &JsonValue) -> Option<ModMetadata> {
+    let loader = json.get("quilt_loader")?;
+    let provides = match loader.get("provides") {
+        Some(JsonValue::Array(x)) => x
+            .iter()
+            .filter_map(|x| match x {
+                JsonValue::String(id) => Some(id.clone()),
+                JsonValue::Object(x) => x.get("id")?.as_str().map(str::to_string),
+                _ => None,
+            })
+            .collect(),
+        _ => vec![],
+    };
+    Some(ModMetadata {
+        id: loader.get("id")?.as_str()?.to_string(),
+        version: loader.get("version")?.as_str()?.to_string(),
+        depends: parse_quilt_relations(loader, "depends"),
+        breaks: parse_quilt_relations(loader, "breaks"),
+        provides,
+    })
+}
+
+/// Reads the mod metadata from a jar, returns `None` if the jar isn't a fabric or quilt mod
+pub(crate) fn read_mod_metadata(path: &Path) -> Option<ModMetadata> {
+    let mut archive = match File::open(path).map(zip::ZipArchive::new) {
+        Ok(Ok(v)) => v,
+        _ => {
+            warn!("Failed to open '{path:#?}' as a jar");
+            return None;
+        }
+    };
+    for (name, parse) in [
+        (
+            "quilt.mod.json",
+            parse_quilt as fn(&JsonValue) -> Option<ModMetadata>,
+        ),
+        ("fabric.mod.json", parse_fabric),
+    ] {
+        let mut contents = String::new();
+        match archive.by_name(name) {
+            Ok(mut file) => {
+                if file.read_to_string(&mut contents).is_err() {
+                    continue;
+                }
+            }
+            Err(_) => continue,
+        }
+        // Fabric accepts raw newlines in strings which serde does not
+        match serde_json::from_str(&contents.replace(['\n', '\r'], " ")) {
+            Ok(json) => return parse(&json),
+            Err(e) => warn!("Failed to parse '{name}' in '{path:#?}': {e}"),
+        }
+    }
+    None
+}
+
+/// Numeric components of a version and whether it's a pre-release, `None` if it isn't a semver-like version
+fn parse_version(version: &str) -> Option<(Vec<u64>, bool)> {
+    let version = version.split('+').next()?;
+    let (core, pre_release) = match version.split_once('-') {
+        Some((core, pre)) => (core, !pre.is_empty()),
+        None => (version, false),
+    };
+    let parts = core
+        .split('.')
+        .map(|x| x.parse().ok())
+        .collect::<Option<Vec<u64>>>()?;
+    Some((parts, pre_release))
+}
+
+fn compare_versions(a: &(Vec<u64>, bool), b: &(Vec<u64>, bool)) -> Ordering {
+    let len = a.0.len().max(b.0.len());
+    for i in 0..len {
+        let ord = a.0.get(i).unwrap_or(&0).cmp(b.0.get(i).unwrap_or(&0));
+        if ord != Ordering::Equal {
+            return ord;
+        }
+    }
+    // Pre-releases come before their release
+    b.1.cmp(&a.1)
+}
+
+/// Checks a single predicate such as `>=1.20`, `~1.20.1` or `1.20.x`, `None` if it can't be evaluated
+fn matches_single(version: &(Vec<u64>, bool), predicate: &str) -> Option<bool> {
+    let predicate = predicate.trim();
+    if predicate.is_empty() || predicate == "*" {
+        return Some(true);
+    }
+    let (op, target) = [">=", "<=", ">", "<", "=", "~", "^"]
+        .iter()
+        .find_map(|op| predicate.strip_prefix(op).map(|x| (*op, x.trim())))
+        .unwrap_or(("", predicate));
+    let wildcard = target
+        .strip_suffix(".x")
+        .or(target.strip_suffix(".X"))
+        .or(target.strip_suffix(".*"));
+    if let Some(prefix) = wildcard {
+        let (prefix, _) = parse_version(prefix)?;
+        if op.is_empty() || op == "=" {
+            return Some(
+                version.0.len() >= prefix.len() && version.0[..prefix.len()] == prefix[..],
+            );
+        }
+    }
+    let target = parse_version(wildcard.unwrap_or(target))?;
+    let ord = compare_versions(version, &target);
+    Some(match op {
+        ">=" => ord != Ordering::Less,
+        "<=" => ord != Ordering::Greater,
+        ">" => ord == Ordering::Greater,
+        "<" => ord == Ordering::Less,
+        "~" => {
+            ord != Ordering::Less
+                && version.0.first() == target.0.first()
+                && version.0.get(1) == target.0.get(1)
+        }
+        "^" => ord != Ordering::Less && version.0.first() == target.0.first(),
+        _ => ord == Ordering::Equal,
+    })
+}
+
+/// Checks if `version` satisfies any of the space separated predicate lists, unparsable versions are assumed to match
+pub(crate) fn version_matches(version: &str, predicates: &[String]) -> bool {
+    let version = match parse_version(version) {
+        Some(v) => v,
+        None => return true,
+    };
+    predicates.is_empty()
+        || predicates.iter().any(|predicate| {
+            predicate
+                .split_whitespace()
+                .all(|x| matches_single(&version, x).unwrap_or(true))
+        })
+}
+
+/// Returns a description of every dependency of the mod that the pack's loader and the java version
+/// Mojang ships for its Minecraft version don't satisfy, the java dependency isn't checked without one
+pub(crate) fn check_compatibility(
+    metadata: &ModMetadata,
+    loader: &Loader,
+    java_version: Option<u32>,
+) -> Vec<String> {
+    let java = java_version.map(|x| x.to_string());
+    let mut provided = vec![("minecraft", loader.minecraft_version.as_str())];
+    if let Some(java) = &java {
+        provided.push(("java", java.as_str()));
+    }
+    match loader.r#type.as_str() {
+        "fabric" => provided.push(("fabricloader", loader.version.as_str())),
+        "quilt" => provided.push(("quilt_loader", loader.version.as_str())),
+        _ => {}
+    }
+    provided
+        .into_iter()
+        .filter_map(|(id, version)| {
+            let predicates = metadata.depends.get(id)?;
+            if version_matches(version, predicates) {
+                None
+            } else {
+                Some(format!(
+                    "requires {id} '{}' but the pack uses '{version}'",
+                    predicates.join(" || ")
+                ))
+            }
+        })
+        .collect()
+}
+
+/// Reads the metadata of every jar in the mods folder
+pub(crate) fn scan_mods(mods_dir: &Path) -> Vec<(PathBuf, ModMetadata)> {
+    let entries = match fs::read_dir(mods_dir) {
+        Ok(v) => v,
+        Err(_) => return vec![],
+    };
+    let mut mods: Vec<(PathBuf, ModMetadata)> = entries
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            if path.extension()? != "jar" {
+                return None;
+            }
+            let metadata = read_mod_metadata(&path)?;
+            Some((path, metadata))
+        })
+        .collect();
+    mods.sort_by(|a, b| a.0.cmp(&b.0));
+    mods
+}
+
+/// Groups jars that declare the same mod id, only ids with more than one jar are returned
+pub(crate) fn find_duplicates(mods: &[(PathBuf, ModMetadata)]) -> Vec<(String, Vec<PathBuf>)> {
+    let mut by_id: HashMap<&str, Vec<PathBuf>> = HashMap::new();
+    for (path, metadata) in mods {
+        by_id
+            .entry(metadata.id.as_str())
+            .or_default()
+            .push(path.clone());
+    }
+    let mut duplicates: Vec<(String, Vec<PathBuf>)> = by_id
+        .into_iter()
+        .filter(|(_, paths)| paths.len() > 1)
+        .map(|(id, paths)| (id.to_string(), paths))
+        .collect();
+    duplicates.sort();
+    duplicates
+}
+
+/// Returns a description of every installed mod that is broken by another installed mod
+pub(crate) fn find_conflicts(mods: &[(PathBuf, ModMetadata)]) -> Vec<String> {
+    let mut conflicts = vec![];
+    for (_, metadata) in mods {
+        for (id, predicates) in &metadata.breaks {
+            for (_, other) in mods {
+                if other == metadata || (&other.id != id && !other.provides.contains(id)) {
+                    continue;
+                }
+                if version_matches(&other.version, predicates) {
+                    conflicts.push(format!(
+                        "'{}' is incompatible with '{}' {}",
+                        metadata.id, other.id, other.version
+                    ));
+                }
+            }
+        }
+    }
+    conflicts.sort();
+    conflicts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn version_matches_predicates() {
+        let cases: &[(&str, &[&str], bool)] = &[
+            // Comparisons
+            ("1.20.1", &[">=1.20"], true),
+            ("1.19.4", &[">=1.20"], false),
+            ("1.20", &[">=1.20.0"], true),
+            ("1.20.1", &["<1.21"], true),
+            ("1.21", &["<1.21"], false),
+            ("1.21-pre1", &["<1.21"], true),
+            ("1.20.1", &["<=1.20.1"], true),
+            ("1.20.2", &[">1.20.1"], true),
+            ("1.20.1", &["=1.20.1"], true),
+            ("1.20.1", &["1.20.1"], true),
+            ("1.20.2", &["1.20.1"], false),
+            // Tilde allows patch updates, caret minor ones
+            ("1.20.4", &["~1.20.1"], true),
+            ("1.21", &["~1.20.1"], false),
+            ("1.20.0", &["~1.20.1"], false),
+            ("1.9.0", &["^1.2.3"], true),
+            ("2.0.0", &["^1.2.3"], false),
+            ("1.2.2", &["^1.2.3"], false),
+            // Wildcards
+            ("1.20.4", &["1.20.x"], true),
+            ("1.20.4", &["1.20.X"], true),
+            ("1.20.4", &["1.20.*"], true),
+            ("1.21", &["1.20.x"], false),
+            ("1.20", &["1.x"], true),
+            ("2.0", &["1.x"], false),
+            ("1.20.1", &["*"], true),
+            ("1.20.1", &[""], true),
+            // Space separated predicates all have to match
+            ("1.20.4", &[">=1.20 <1.21"], true),
+            ("1.21", &[">=1.20 <1.21"], false),
+            ("1.19", &[">=1.20 <1.21"], false),
+            // Any predicate of an array can match
+            ("1.19.2", &["1.19.x", "1.20.x"], true),
+            ("1.20.1", &["1.19.x", "1.20.x"], true),
+            ("1.18.2", &["1.19.x", "1.20.x"], false),
+            ("1.20.1", &[], true),
+            // Versions and predicates that can't be evaluated are assumed to match
+            ("23w13a", &[">=1.20"], true),
+            ("1.20.1", &[">=latest"], true),
+            ("0.15.11+build.1", &[">=0.15.0"], true),
+        ];
+        for (version, predicates, expected) in cases {
+            let predicates: Vec<String> = predicates.iter().map(|x| x.to_string()).collect();
+            assert_eq!(
+                version_matches(version, &predicates),
+                *expected,
+                "'{version}' against {predicates:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn predicates_flatten_json() {
+        let json = serde_json::json!({ "any": [">=1.20", ["1.19.x"]] });
+        assert_eq!(predicates(&json), [">=1.20", "1.19.x"]);
+        assert_eq!(predicates(&serde_json::json!("*")), ["*"]);
+        assert!(predicates(&serde_json::json!(1)).is_empty());
+    }
+}
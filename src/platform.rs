@@ -0,0 +1,81 @@
+// The operating system and architecture manifest conditions are evaluated against
+use std::sync::OnceLock;
+
+use platform_info::{PlatformInfo, PlatformInfoAPI, UNameAPI};
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Platform {
+    /// `linux`, `macos` or `windows`
+    pub os: String,
+    /// `x86_64`, `aarch64`, `x86` or `arm`
+    pub arch: String,
+}
+
+impl Platform {
+    pub(crate) fn from_info(info: &PlatformInfo) -> Self {
+        Platform::from_uname(
+            &info.sysname().to_string_lossy(),
+            &info.machine().to_string_lossy(),
+        )
+    }
+
+    /// Normalizes the `uname` style names, which differ between operating systems (e.g. `arm64` on
+    /// macOS and `aarch64` on Linux)
+    fn from_uname(sysname: &str, machine: &str) -> Self {
+        let os = match sysname.to_lowercase().as_str() {
+            "darwin" => String::from("macos"),
+            x if x.starts_with("windows") => String::from("windows"),
+            x => x.to_string(),
+        };
+        let arch = match machine.to_lowercase().as_str() {
+            "x86_64" | "amd64" | "x64" => String::from("x86_64"),
+            "aarch64" | "arm64" => String::from("aarch64"),
+            "x86" | "i386" | "i486" | "i586" | "i686" => String::from("x86"),
+            x if x.starts_with("arm") => String::from("arm"),
+            x => x.to_string(),
+        };
+        Platform { os, arch }
+    }
+}
+
+/// The platform the installer is running on
+pub(crate) fn current() -> &'static Platform {
+    static PLATFORM: OnceLock<Platform> = OnceLock::new();
+    PLATFORM.get_or_init(|| {
+        Platform::from_info(&PlatformInfo::new().expect("Unable to determine platform info"))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_uname_normalizes_names() {
+        let cases = [
+            (("Linux", "x86_64"), ("linux", "x86_64")),
+            (("Darwin", "arm64"), ("macos", "aarch64")),
+            (("Darwin", "x86_64"), ("macos", "x86_64")),
+            (("Windows_NT", "AMD64"), ("windows", "x86_64")),
+            (("Windows_NT", "ARM64"), ("windows", "aarch64")),
+            (("Linux", "aarch64"), ("linux", "aarch64")),
+            (("Linux", "i686"), ("linux", "x86")),
+            (("Linux", "armv7l"), ("linux", "arm")),
+            (("FreeBSD", "riscv64"), ("freebsd", "riscv64")),
+        ];
+        for ((sysname, machine), (os, arch)) in cases {
+            assert_eq!(
+                Platform::from_uname(sysname, machine),
+                Platform {
+                    os: String::from(os),
+                    arch: String::from(arch),
+                },
+                "'{sysname}' '{machine}'"
+            );
+        }
+    }
+    #[test]
+    fn current_matches_target() {
+        assert_eq!(current().os, std::env::consts::OS);
+    }
+}
@@ -0,0 +1,31 @@
+// JSON Schema of `manifest.json`, shipped as `manifest.schema.json` for editor autocompletion
+use jsonschema::JSONSchema;
+use schemars::schema_for;
+use serde_json::Value as JsonValue;
+
+use crate::Manifest;
+
+pub(crate) fn manifest_schema() -> JsonValue {
+    serde_json::to_value(schema_for!(Manifest)).expect("Failed to serialize manifest schema")
+}
+
+/// Checks `manifest` against the schema, every violation is reported with the path of the offending value
+pub(crate) fn validate_manifest(manifest: &JsonValue) -> Result<(), String> {
+    let schema = manifest_schema();
+    let compiled = JSONSchema::compile(&schema).expect("Invalid manifest schema");
+    let errors: Vec<String> = match compiled.validate(manifest) {
+        Ok(_) => return Ok(()),
+        Err(errors) => errors
+            .map(|e| {
+                let path = e.instance_path.to_string();
+                let path = if path.is_empty() {
+                    String::from("/")
+                } else {
+                    path
+                };
+                format!("{path}: {e}")
+            })
+            .collect(),
+    };
+    Err(format!("Invalid manifest:\n{}", errors.join("\n")))
+}